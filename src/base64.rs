@@ -0,0 +1,88 @@
+// Base64 as used by the `!!binary` type: the standard alphabet with padding,
+// in which line breaks and other whitespace may appear anywhere.
+
+use std::str;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let b0 = chunk[0];
+        let b1 = chunk.get(1).copied().unwrap_or(0);
+        let b2 = chunk.get(2).copied().unwrap_or(0);
+        let sextets = [
+            b0 >> 2,
+            (b0 & 0x03) << 4 | b1 >> 4,
+            (b1 & 0x0f) << 2 | b2 >> 6,
+            b2 & 0x3f,
+        ];
+        for (i, &sextet) in sextets.iter().enumerate() {
+            encoded.push(if i <= chunk.len() {
+                ALPHABET[sextet as usize] as char
+            } else {
+                '='
+            });
+        }
+    }
+    encoded
+}
+
+// Data longer than one line is split into lines of this length, as in MIME.
+const LINE_WIDTH: usize = 76;
+
+// Encodes on a single line if it fits, and otherwise as lines each terminated
+// by a line break, suitable for a literal block scalar.
+pub(crate) fn encode_lines(bytes: &[u8]) -> String {
+    let encoded = encode(bytes);
+    if encoded.len() <= LINE_WIDTH {
+        return encoded;
+    }
+    let mut lines = String::with_capacity(encoded.len() + encoded.len() / LINE_WIDTH + 1);
+    for line in encoded.as_bytes().chunks(LINE_WIDTH) {
+        lines.push_str(str::from_utf8(line).unwrap());
+        lines.push('\n');
+    }
+    lines
+}
+
+pub(crate) fn decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    let mut padding = 0;
+    let mut len = 0;
+    for b in encoded.bytes() {
+        let sextet = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => {
+                padding += 1;
+                len += 1;
+                continue;
+            }
+            b' ' | b'\t' | b'\n' | b'\r' => continue,
+            _ => return None,
+        };
+        if padding > 0 {
+            return None;
+        }
+        buffer = buffer << 6 | u32::from(sextet);
+        bits += 6;
+        len += 1;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    // Padding is optional, but where present it must complete the last group,
+    // and the bits left over must be zero.
+    if len % 4 == 1 || (padding > 0 && len % 4 != 0) || padding > 2 || buffer != 0 {
+        return None;
+    }
+    Some(bytes)
+}
@@ -1,13 +1,20 @@
-use crate::error::{self, Error, ErrorImpl};
+use crate::base64;
+use crate::error::{self, Error, ErrorImpl, Location};
 use crate::libyaml::error::Mark;
 use crate::libyaml::parser::{MappingStart, Scalar, ScalarStyle, SequenceStart};
 use crate::libyaml::tag::Tag;
 use crate::loader::{Document, Loader};
 use crate::path::Path;
-use serde::de::value::StrDeserializer;
+use crate::spanned;
+use crate::timestamp::{Time, Timestamp};
+use crate::value::{self, Value};
+use serde::de::value::{BorrowedStrDeserializer, SeqDeserializer, StrDeserializer};
 use serde::de::{
     self, Deserialize, DeserializeOwned, DeserializeSeed, Expected, IgnoredAny, Unexpected, Visitor,
 };
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::{self, HashMap};
+use std::collections::{BTreeSet, HashSet};
 use std::fmt;
 use std::io;
 use std::mem;
@@ -57,6 +64,291 @@ type Result<T, E = Error> = std::result::Result<T, E>;
 /// ```
 pub struct Deserializer<'de> {
     progress: Progress<'de>,
+    options: DeserializerOptions,
+}
+
+/// Configuration of a [`Deserializer`]: limits on the input it processes, and
+/// how it treats duplicate keys.
+///
+/// The default limits accept any input that the deserializer would otherwise
+/// accept, apart from a recursion limit of 128 and an alias expansion budget
+/// proportional to the size of the document. When deserializing YAML from an
+/// untrusted source, tighter limits bound the time and memory spent on each
+/// document.
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_yaml::{Deserializer, DeserializerOptions, Value};
+///
+/// let options = DeserializerOptions::new()
+///     .max_depth(16)
+///     .max_scalar_length(1024);
+///
+/// let de = Deserializer::from_str("k: [1, 2, 3]").with_options(options);
+/// let value = Value::deserialize(de).unwrap();
+/// # let _ = value;
+/// ```
+#[derive(Clone, Debug)]
+pub struct DeserializerOptions {
+    pub(crate) depth: usize,
+    pub(crate) alias_expansions: Option<usize>,
+    pub(crate) events: Option<usize>,
+    pub(crate) scalar_length: Option<usize>,
+    pub(crate) document_bytes: Option<usize>,
+    pub(crate) duplicate_keys: Option<DuplicateKeyPolicy>,
+    pub(crate) merge_keys: bool,
+    pub(crate) preserve_anchors: bool,
+    pub(crate) schema: Schema,
+    pub(crate) max_errors: usize,
+}
+
+/// Rules for resolving the type of plain scalars, such as `true` or `0x1F`,
+/// that have no explicit tag.
+///
+/// | | `Core` | `Json` | `Yaml11` |
+/// |---|---|---|---|
+/// | `~`, `Null` | null | string | null |
+/// | `True`, `FALSE` | bool | string | bool |
+/// | `yes`, `no`, `on`, `off` | string | string | bool |
+/// | `0x1F` | int | string | int |
+/// | `0o17` | int | string | string |
+/// | `0777` | string | string | int (octal) |
+/// | `1_000`, `1:30` | string | string | int |
+/// | `.inf`, `.nan` | float | string | float |
+/// | `1e3` | float | float | string |
+///
+/// By default the serializer quotes any string that would be read back as
+/// something other than a string under the YAML 1.2 core schema. Output meant
+/// for readers of YAML 1.1 should be written with
+/// [`QuotePolicy::WhenAmbiguous`], which also quotes strings that the YAML 1.1
+/// rules would misread, so that it means the same thing under every schema.
+/// Timestamps are the exception: they are written unquoted, as other YAML
+/// libraries expect, and a plain timestamp deserializes into a `String` or a
+/// [`Timestamp`] under every schema. Only a scalar explicitly tagged
+/// `!!timestamp` is represented in a [`Value`] as a timestamp; a plain one is
+/// a string.
+///
+/// [`QuotePolicy::WhenAmbiguous`]: crate::QuotePolicy::WhenAmbiguous
+/// [`Timestamp`]: crate::Timestamp
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Schema {
+    /// The YAML 1.2 core schema. This is the default.
+    Core,
+    /// The YAML 1.2 JSON schema, which recognizes only `null`, `true`,
+    /// `false`, and numbers in JSON syntax. Any other plain scalar is a
+    /// string.
+    Json,
+    /// The implicit types of YAML 1.1, as resolved by PyYAML and other tools
+    /// predating YAML 1.2: `yes`/`no`/`on`/`off` booleans, `0777` octal and
+    /// `0b1010` binary integers, `_` digit separators, and base 60 numbers
+    /// like `1:30`.
+    Yaml11,
+}
+
+/// How a [`Deserializer`] treats a mapping that contains the same key more than
+/// once.
+///
+/// Keys are compared as [`Value`]s, so `1` and `0x1` are the same key, while
+/// `1` and `"1"` are not.
+///
+/// [`Value`]: crate::Value
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DuplicateKeyPolicy {
+    /// Fail with an error that reports the location of both entries.
+    Error,
+    /// Keep the first entry with any given key, skipping later ones.
+    FirstWins,
+    /// Keep the last entry with any given key, skipping earlier ones.
+    LastWins,
+}
+
+impl DeserializerOptions {
+    /// Creates the default set of options.
+    pub fn new() -> Self {
+        DeserializerOptions {
+            depth: 128,
+            alias_expansions: None,
+            events: None,
+            scalar_length: None,
+            document_bytes: None,
+            duplicate_keys: None,
+            merge_keys: false,
+            preserve_anchors: false,
+            schema: Schema::Core,
+            max_errors: 100,
+        }
+    }
+
+    /// Maximum nesting depth of sequences and mappings, counting those reached
+    /// through an alias. Defaults to 128.
+    ///
+    /// Exceeding it fails with "recursion limit exceeded".
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.depth = max_depth;
+        self
+    }
+
+    /// Maximum number of times aliases may be expanded while deserializing a
+    /// single document. Defaults to 100 times the number of events in the
+    /// document.
+    ///
+    /// Exceeding it fails with "repetition limit exceeded".
+    pub fn max_alias_expansions(mut self, max_alias_expansions: usize) -> Self {
+        self.alias_expansions = Some(max_alias_expansions);
+        self
+    }
+
+    /// Maximum number of parser events (scalars, aliases, and the start and
+    /// end of each sequence and mapping) in a single document. Unlimited by
+    /// default.
+    ///
+    /// Exceeding it fails with "event limit exceeded".
+    pub fn max_events(mut self, max_events: usize) -> Self {
+        self.events = Some(max_events);
+        self
+    }
+
+    /// Maximum length in bytes of any single scalar, after unescaping.
+    /// Unlimited by default.
+    ///
+    /// Exceeding it fails with "scalar length limit exceeded".
+    pub fn max_scalar_length(mut self, max_scalar_length: usize) -> Self {
+        self.scalar_length = Some(max_scalar_length);
+        self
+    }
+
+    /// Maximum size in bytes of the input making up a single document.
+    /// Unlimited by default.
+    ///
+    /// Exceeding it fails with "document size limit exceeded".
+    pub fn max_document_bytes(mut self, max_document_bytes: usize) -> Self {
+        self.document_bytes = Some(max_document_bytes);
+        self
+    }
+
+    /// Apply a policy to mappings that contain duplicate keys, regardless of
+    /// the type being deserialized.
+    ///
+    /// By default every entry is passed through to the type's `Deserialize`
+    /// impl, which decides what to do with duplicates. For example `Mapping`
+    /// and structs reject them, while `BTreeMap` and `HashMap` keep the last
+    /// value.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeyPolicy) -> Self {
+        self.duplicate_keys = Some(policy);
+        self
+    }
+
+    /// Resolve `<<` merge keys while deserializing, as [`Value::apply_merge`]
+    /// would. Disabled by default, in which case `<<` is an ordinary key.
+    ///
+    /// The value of a `<<` entry must be a mapping or a sequence of mappings,
+    /// typically aliases. Entries of the merged mappings are deserialized as
+    /// if they were written out in place of the `<<` entry, except that keys
+    /// written out explicitly take precedence, and earlier mappings in a
+    /// sequence take precedence over later ones.
+    ///
+    /// ```
+    /// # use serde_derive::Deserialize;
+    /// use serde::Deserialize;
+    /// use serde_yaml::{Deserializer, DeserializerOptions};
+    /// use std::collections::BTreeMap;
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// struct Job {
+    ///     image: String,
+    ///     script: String,
+    /// }
+    ///
+    /// let yaml = "
+    /// defaults: &defaults
+    ///   image: rust
+    ///   script: cargo build
+    /// test:
+    ///   <<: *defaults
+    ///   script: cargo test
+    /// ";
+    ///
+    /// let options = DeserializerOptions::new().merge_keys(true);
+    /// let de = Deserializer::from_str(yaml).with_options(options);
+    /// let jobs = BTreeMap::<String, Job>::deserialize(de).unwrap();
+    /// assert_eq!(jobs["test"].image, "rust");
+    /// assert_eq!(jobs["test"].script, "cargo test");
+    /// ```
+    ///
+    /// [`Value::apply_merge`]: crate::Value::apply_merge
+    pub fn merge_keys(mut self, enable: bool) -> Self {
+        self.merge_keys = enable;
+        self
+    }
+
+    /// Keep anchors and aliases when deserializing a [`Value`], instead of
+    /// expanding each alias into a copy of the node it refers to. Disabled by
+    /// default.
+    ///
+    /// An anchored node `&name node` is represented as a [`TaggedValue`] with
+    /// tag `&name`, and an alias `*name` as a `TaggedValue` with tag `*name`
+    /// and a null value. A [`Serializer`][crate::Serializer] with
+    /// [`SerializerOptions::preserve_anchors`][crate::SerializerOptions::preserve_anchors]
+    /// writes these back as anchors and aliases, so that loading and saving a
+    /// document keeps its shape.
+    ///
+    /// This only affects types that deserialize through
+    /// `deserialize_any`, such as `Value`; structs and other types that ask
+    /// for specific data still see aliases expanded.
+    ///
+    /// ```
+    /// use serde::{Deserialize, Serialize};
+    /// use serde_yaml::value::{Tag, TaggedValue};
+    /// use serde_yaml::{Deserializer, DeserializerOptions, Serializer, SerializerOptions, Value};
+    ///
+    /// let yaml = "base: &base {image: rust}\njob: *base\n";
+    ///
+    /// let options = DeserializerOptions::new().preserve_anchors(true);
+    /// let de = Deserializer::from_str(yaml).with_options(options);
+    /// let value = Value::deserialize(de).unwrap();
+    ///
+    /// let alias = TaggedValue {
+    ///     tag: Tag::new("*base"),
+    ///     value: Value::Null,
+    /// };
+    /// assert_eq!(value["job"], Value::Tagged(Box::new(alias)));
+    /// assert_eq!(value["base"]["image"], "rust");
+    ///
+    /// let options = SerializerOptions::new().preserve_anchors(true);
+    /// let mut ser = Serializer::new(Vec::new()).with_options(options);
+    /// value.serialize(&mut ser).unwrap();
+    ///
+    /// let yaml = String::from_utf8(ser.into_inner().unwrap()).unwrap();
+    /// assert_eq!(yaml, "base: &base\n  image: rust\njob: *base\n");
+    /// ```
+    ///
+    /// [`Value`]: crate::Value
+    /// [`TaggedValue`]: crate::value::TaggedValue
+    pub fn preserve_anchors(mut self, enable: bool) -> Self {
+        self.preserve_anchors = enable;
+        self
+    }
+
+    /// Select the rules for resolving the type of untagged plain scalars.
+    /// Defaults to [`Schema::Core`].
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Maximum number of failed passes [`Deserializer::collect_errors`] makes
+    /// over a document, and so of errors it reports, before it gives up on
+    /// the rest of the document. Defaults to 100.
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+}
+
+impl Default for DeserializerOptions {
+    fn default() -> Self {
+        DeserializerOptions::new()
+    }
 }
 
 pub(crate) enum Progress<'de> {
@@ -72,13 +364,19 @@ impl<'de> Deserializer<'de> {
     /// Creates a YAML deserializer from a `&str`.
     pub fn from_str(s: &'de str) -> Self {
         let progress = Progress::Str(s);
-        Deserializer { progress }
+        Deserializer {
+            progress,
+            options: DeserializerOptions::new(),
+        }
     }
 
     /// Creates a YAML deserializer from a `&[u8]`.
     pub fn from_slice(v: &'de [u8]) -> Self {
         let progress = Progress::Slice(v);
-        Deserializer { progress }
+        Deserializer {
+            progress,
+            options: DeserializerOptions::new(),
+        }
     }
 
     /// Creates a YAML deserializer from an `io::Read`.
@@ -86,12 +384,128 @@ impl<'de> Deserializer<'de> {
     /// Reader-based deserializers do not support deserializing borrowed types
     /// like `&str`, since the `std::io::Read` trait has no non-copying methods
     /// -- everything it does involves copying bytes out of the data source.
+    ///
+    /// The input is read incrementally. When iterating over a multi-document
+    /// stream, each document is yielded as soon as it has been parsed, without
+    /// waiting for the rest of the input.
     pub fn from_reader<R>(rdr: R) -> Self
     where
         R: io::Read + 'de,
     {
         let progress = Progress::Read(Box::new(rdr));
-        Deserializer { progress }
+        Deserializer {
+            progress,
+            options: DeserializerOptions::new(),
+        }
+    }
+
+    /// Replaces the limits applied by this deserializer.
+    ///
+    /// When iterating over a multi-document stream, the documents produced by
+    /// the iterator inherit the options in effect at the time.
+    pub fn with_options(mut self, options: DeserializerOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Deserializes a single document, continuing past errors in order to
+    /// report as many of them as possible in one go.
+    ///
+    /// Whenever some part of the document fails to deserialize, the error is
+    /// recorded and deserialization starts over with the failing sequence
+    /// element or mapping entry skipped. Errors that are only a consequence of
+    /// skipping, such as a missing field in the struct containing the entry,
+    /// are not reported. This continues until deserialization succeeds, the
+    /// document as a whole fails, or [`DeserializerOptions::max_errors`]
+    /// passes have failed. Each error carries the path and location of the
+    /// offending node.
+    ///
+    /// Since every failure takes another pass over the document, the time
+    /// this takes grows with the size of the document times
+    /// `max_errors`. Each pass is subject to the configured limits on its
+    /// own, including a fresh [`DeserializerOptions::max_alias_expansions`]
+    /// budget, and collection stops at the first error due to one of them.
+    ///
+    /// ```
+    /// # use serde_derive::Deserialize;
+    /// use serde::Deserialize;
+    /// use serde_yaml::Deserializer;
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// struct Server {
+    ///     host: String,
+    ///     port: u16,
+    ///     workers: u8,
+    /// }
+    ///
+    /// let yaml = "host: localhost\nport: http\nworkers: 1000\n";
+    /// let errors = Deserializer::from_str(yaml)
+    ///     .collect_errors::<Server>()
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(errors.len(), 2);
+    /// assert_eq!(
+    ///     errors[0].to_string(),
+    ///     "port: invalid type: string \"http\", expected u16 at line 2 column 7",
+    /// );
+    /// assert_eq!(
+    ///     errors[1].to_string(),
+    ///     "workers: invalid value: integer `1000`, expected u8 at line 3 column 10",
+    /// );
+    /// ```
+    pub fn collect_errors<T>(self) -> Result<T, Vec<Error>>
+    where
+        T: Deserialize<'de>,
+    {
+        match self.de(|state| Ok(state.collect_errors())) {
+            Ok(result) => result,
+            Err(err) => Err(vec![err]),
+        }
+    }
+
+    /// Deserializes a single document, calling `callback` with the path and
+    /// location of every mapping key whose value is discarded as
+    /// [`IgnoredAny`].
+    ///
+    /// This is how a derived `Deserialize` impl skips over fields that the
+    /// struct does not have, so the callback makes it possible to warn about
+    /// typos in configuration files without rejecting them the way
+    /// `#[serde(deny_unknown_fields)]` would. Keys inside a struct with a
+    /// `#[serde(flatten)]` field are not reported, as the struct buffers them
+    /// instead of ignoring them.
+    ///
+    /// ```
+    /// # use serde_derive::Deserialize;
+    /// use serde::Deserialize;
+    /// use serde_yaml::Deserializer;
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// struct Package {
+    ///     name: String,
+    ///     version: String,
+    /// }
+    ///
+    /// let yaml = "name: serde_yaml\nversoin: 0.9\n";
+    /// let mut ignored = Vec::new();
+    /// let result = Deserializer::from_str(yaml).deserialize_with_ignored_keys(
+    ///     |path, location| ignored.push((path.to_owned(), location.line())),
+    /// );
+    ///
+    /// // The typo leaves `version` missing.
+    /// let error = result.map(|_: Package| ()).unwrap_err();
+    /// assert_eq!(error.to_string(), "missing field `version`");
+    /// assert_eq!(ignored, [("versoin".to_owned(), 2)]);
+    /// ```
+    ///
+    /// [`IgnoredAny`]: serde::de::IgnoredAny
+    pub fn deserialize_with_ignored_keys<T, F>(self, callback: F) -> Result<T>
+    where
+        T: Deserialize<'de>,
+        F: FnMut(&str, Location),
+    {
+        let callback = RefCell::new(callback);
+        let ignored_key = |path: &str, location: Location| (callback.borrow_mut())(path, location);
+        self.de(|state| state.deserialize_with_ignored_keys(&ignored_key))
     }
 
     fn de<T>(
@@ -106,11 +520,15 @@ impl<'de> Deserializer<'de> {
             Progress::Document(document) => {
                 let t = f(&mut DeserializerFromEvents {
                     document: &document,
+                    options: &self.options,
                     pos: &mut pos,
                     jumpcount: &mut jumpcount,
                     path: Path::Root,
-                    remaining_depth: 128,
+                    remaining_depth: self.options.depth,
                     current_enum: None,
+                    recovery: None,
+                    ignored_key: None,
+                    entry_key: None,
                 })?;
                 if let Some(parse_error) = document.error {
                     return Err(error::shared(parse_error));
@@ -121,22 +539,26 @@ impl<'de> Deserializer<'de> {
         }
 
         let mut loader = Loader::new(self.progress)?;
-        let document = match loader.next_document() {
+        let document = match loader.next_document(&self.options) {
             Some(document) => document,
             None => return Err(error::new(ErrorImpl::EndOfStream)),
         };
         let t = f(&mut DeserializerFromEvents {
             document: &document,
+            options: &self.options,
             pos: &mut pos,
             jumpcount: &mut jumpcount,
             path: Path::Root,
-            remaining_depth: 128,
+            remaining_depth: self.options.depth,
             current_enum: None,
+            recovery: None,
+            ignored_key: None,
+            entry_key: None,
         })?;
         if let Some(parse_error) = document.error {
             return Err(error::shared(parse_error));
         }
-        if loader.next_document().is_none() {
+        if loader.next_document(&self.options).is_none() {
             Ok(t)
         } else {
             Err(error::new(ErrorImpl::MoreThanOneDocument))
@@ -150,15 +572,17 @@ impl<'de> Iterator for Deserializer<'de> {
     fn next(&mut self) -> Option<Self> {
         match &mut self.progress {
             Progress::Iterable(loader) => {
-                let document = loader.next_document()?;
+                let document = loader.next_document(&self.options)?;
                 return Some(Deserializer {
                     progress: Progress::Document(document),
+                    options: self.options.clone(),
                 });
             }
             Progress::Document(_) => return None,
             Progress::Fail(err) => {
                 return Some(Deserializer {
                     progress: Progress::Fail(Arc::clone(err)),
+                    options: self.options.clone(),
                 });
             }
             _ => {}
@@ -176,6 +600,7 @@ impl<'de> Iterator for Deserializer<'de> {
                 self.progress = Progress::Fail(Arc::clone(&fail));
                 Some(Deserializer {
                     progress: Progress::Fail(fail),
+                    options: self.options.clone(),
                 })
             }
         }
@@ -431,17 +856,82 @@ pub(crate) enum Event<'de> {
 
 struct DeserializerFromEvents<'de, 'document> {
     document: &'document Document<'de>,
+    options: &'document DeserializerOptions,
     pos: &'document mut usize,
     jumpcount: &'document mut usize,
     path: Path<'document>,
-    remaining_depth: u8,
+    remaining_depth: usize,
     current_enum: Option<CurrentEnum<'document>>,
+    recovery: Option<&'document Recovery>,
+    /// Callback of [`Deserializer::deserialize_with_ignored_keys`].
+    ignored_key: Option<&'document IgnoredKeyFn<'document>>,
+    /// Location of the key, if this deserializes the value of a mapping entry.
+    entry_key: Option<Mark>,
+}
+
+type IgnoredKeyFn<'a> = dyn Fn(&str, Location) + 'a;
+
+/// State shared by the passes of [`Deserializer::collect_errors`].
+struct Recovery {
+    /// Positions of the nodes that failed to deserialize in an earlier pass.
+    /// Sequences skip these elements and mappings skip these entries.
+    masked: BTreeSet<usize>,
+    /// Position of the innermost node that failed during the current pass.
+    failure: Cell<Option<usize>>,
+}
+
+impl Recovery {
+    fn observe<T>(&self, pos: usize, result: &Result<T>) {
+        match result {
+            // Any failure below here was handled by a Deserialize impl.
+            Ok(_) => self.failure.set(None),
+            Err(_) => {
+                if self.failure.get().is_none() {
+                    self.failure.set(Some(pos));
+                }
+            }
+        }
+    }
+
+    /// Whether the node at `pos`, or anything it refers to through an alias,
+    /// contains a masked node.
+    fn contains_masked(&self, document: &Document, pos: usize) -> bool {
+        let mut visited = HashSet::new();
+        let mut pending = vec![pos];
+        while let Some(mut pos) = pending.pop() {
+            let mut depth = 0usize;
+            loop {
+                if self.masked.contains(&pos) {
+                    return true;
+                }
+                match document.events.get(pos) {
+                    Some((Event::Alias(id), _, _)) => {
+                        if let Some(&target) = document.aliases.get(id) {
+                            if visited.insert(target) {
+                                pending.push(target);
+                            }
+                        }
+                    }
+                    Some((Event::SequenceStart(_) | Event::MappingStart(_), _, _)) => depth += 1,
+                    Some((Event::SequenceEnd | Event::MappingEnd, _, _)) => depth -= 1,
+                    Some((Event::Scalar(_) | Event::Void, _, _)) => {}
+                    None => break,
+                }
+                pos += 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+        }
+        false
+    }
 }
 
 #[derive(Copy, Clone)]
 struct CurrentEnum<'document> {
     name: Option<&'static str>,
     tag: &'document str,
+    is_anchor: bool,
 }
 
 impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
@@ -451,7 +941,7 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
 
     fn peek_event_mark(&self) -> Result<(&'document Event<'de>, Mark)> {
         match self.document.events.get(*self.pos) {
-            Some((event, mark)) => Ok((event, *mark)),
+            Some((event, mark, _end)) => Ok((event, *mark)),
             None => Err(match &self.document.error {
                 Some(parse_error) => error::shared(Arc::clone(parse_error)),
                 None => error::new(ErrorImpl::EndOfStream),
@@ -459,6 +949,25 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
         }
     }
 
+    // Where the node whose first event is at `pos` ends.
+    fn node_end(&self, pos: usize) -> Mark {
+        let events = &self.document.events;
+        let mut depth = 0;
+        for (event, _start, end) in &events[pos..] {
+            match event {
+                Event::SequenceStart(_) | Event::MappingStart(_) => depth += 1,
+                Event::SequenceEnd | Event::MappingEnd => depth -= 1,
+                Event::Alias(_) | Event::Scalar(_) | Event::Void => {}
+            }
+            if depth <= 0 {
+                return *end;
+            }
+        }
+        // The document was cut short by a parse error.
+        let (_event, _start, end) = events.last().unwrap();
+        *end
+    }
+
     fn next_event(&mut self) -> Result<&'document Event<'de>> {
         self.next_event_mark().map(|(event, _mark)| event)
     }
@@ -471,27 +980,148 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
         })
     }
 
+    fn collect_errors<T>(&mut self) -> Result<T, Vec<Error>>
+    where
+        T: Deserialize<'de>,
+    {
+        let root = *self.pos;
+        let mut recovery = Recovery {
+            masked: BTreeSet::new(),
+            failure: Cell::new(None),
+        };
+        let mut errors = Vec::new();
+        let mut passes = 0;
+        loop {
+            let mut pos = root;
+            let mut jumpcount = 0;
+            let result = T::deserialize(&mut DeserializerFromEvents {
+                document: self.document,
+                options: self.options,
+                pos: &mut pos,
+                jumpcount: &mut jumpcount,
+                path: Path::Root,
+                remaining_depth: self.remaining_depth,
+                current_enum: None,
+                // A parse error truncates the document, so every pass would
+                // fail at the end of the events regardless of what is skipped.
+                recovery: if self.document.error.is_none() {
+                    Some(&recovery)
+                } else {
+                    None
+                },
+                ignored_key: self.ignored_key,
+                entry_key: None,
+            });
+            let err = match result {
+                Ok(value) if errors.is_empty() => return Ok(value),
+                Ok(_value) => return Err(errors),
+                Err(err) => err,
+            };
+            passes += 1;
+            let failed = recovery.failure.take().unwrap_or(root);
+            // The limits apply to the document as a whole, not to the node
+            // that happened to cross them.
+            let limit_exceeded = err.kind().is_limit_exceeded();
+            if limit_exceeded || !recovery.contains_masked(self.document, failed) {
+                errors.push(err);
+            }
+            // A pass that only fails as a consequence of an earlier error
+            // records nothing, but still counts towards max_errors so that
+            // the number of passes stays bounded.
+            if failed == root
+                || limit_exceeded
+                || passes >= self.options.max_errors
+                || !recovery.masked.insert(failed)
+            {
+                return Err(errors);
+            }
+        }
+    }
+
+    // Skips the next sequence element if it failed in an earlier pass of
+    // collect_errors.
+    fn skip_masked_element(&mut self) -> Result<bool> {
+        match self.recovery {
+            Some(recovery) if recovery.masked.contains(self.pos) => {
+                self.ignore_any()?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    // Skips the next mapping entry if its key or value failed in an earlier
+    // pass of collect_errors.
+    fn skip_masked_entry(&mut self) -> Result<bool> {
+        let recovery = match self.recovery {
+            Some(recovery) => recovery,
+            None => return Ok(false),
+        };
+        let key_pos = *self.pos;
+        self.ignore_any()?;
+        if recovery.masked.contains(&key_pos) || recovery.masked.contains(self.pos) {
+            self.ignore_any()?;
+            Ok(true)
+        } else {
+            *self.pos = key_pos;
+            Ok(false)
+        }
+    }
+
+    fn deserialize_with_ignored_keys<T>(
+        &mut self,
+        ignored_key: &dyn Fn(&str, Location),
+    ) -> Result<T>
+    where
+        T: Deserialize<'de>,
+    {
+        T::deserialize(&mut DeserializerFromEvents {
+            document: self.document,
+            options: self.options,
+            pos: self.pos,
+            jumpcount: self.jumpcount,
+            path: self.path,
+            remaining_depth: self.remaining_depth,
+            current_enum: None,
+            recovery: None,
+            ignored_key: Some(ignored_key),
+            entry_key: None,
+        })
+    }
+
     fn jump<'anchor>(
         &'anchor mut self,
         pos: &'anchor mut usize,
     ) -> Result<DeserializerFromEvents<'de, 'anchor>> {
+        *pos = self.alias_target(*pos)?;
+        Ok(DeserializerFromEvents {
+            document: self.document,
+            options: self.options,
+            pos,
+            jumpcount: self.jumpcount,
+            path: Path::Alias { parent: &self.path },
+            remaining_depth: self.remaining_depth,
+            current_enum: None,
+            recovery: self.recovery,
+            ignored_key: self.ignored_key,
+            entry_key: None,
+        })
+    }
+
+    fn alias_target(&mut self, id: usize) -> Result<usize> {
         *self.jumpcount += 1;
-        if *self.jumpcount > self.document.events.len() * 100 {
-            return Err(error::new(ErrorImpl::RepetitionLimitExceeded));
-        }
-        match self.document.aliases.get(pos) {
-            Some(found) => {
-                *pos = *found;
-                Ok(DeserializerFromEvents {
-                    document: self.document,
-                    pos,
-                    jumpcount: self.jumpcount,
-                    path: Path::Alias { parent: &self.path },
-                    remaining_depth: self.remaining_depth,
-                    current_enum: None,
-                })
-            }
-            None => panic!("unresolved alias: {}", *pos),
+        let max_alias_expansions = match self.options.alias_expansions {
+            Some(max_alias_expansions) => max_alias_expansions,
+            None => self.document.events.len() * 100,
+        };
+        if *self.jumpcount > max_alias_expansions {
+            // Every caller has already consumed the Alias event.
+            let mark = self.document.events[*self.pos - 1].1;
+            return Err(error::new(ErrorImpl::RepetitionLimitExceeded(mark)));
+        }
+        match self.document.aliases.get(&id) {
+            Some(found) => Ok(*found),
+            None => panic!("unresolved alias: {}", id),
         }
     }
 
@@ -552,20 +1182,157 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
     where
         V: Visitor<'de>,
     {
+        let entries = if self.options.duplicate_keys.is_some() || self.options.merge_keys {
+            Some(self.mapping_entries()?)
+        } else {
+            None
+        };
         let (value, len) = self.recursion_check(mark, |de| {
             let mut map = MapAccess {
                 empty: false,
                 de,
                 len: 0,
                 key: None,
+                key_mark: None,
+                entries: entries.as_ref(),
             };
             let value = visitor.visit_map(&mut map)?;
             Ok((value, map.len))
         })?;
-        self.end_mapping(len)?;
+        self.end_mapping(len, entries.as_ref())?;
         Ok(value)
     }
 
+    // Looks ahead through the entries of the mapping whose MappingStart was
+    // just consumed, and picks which of them to deserialize.
+    fn mapping_entries(&mut self) -> Result<MappingEntries> {
+        let start = *self.pos;
+        let (entries, end) = self.scan_mapping()?;
+        *self.pos = start;
+        Ok(MappingEntries {
+            keys: entries.into_iter().map(|entry| entry.pos).collect(),
+            end,
+        })
+    }
+
+    // Returns the entries of the mapping starting at the current position
+    // after applying the duplicate key policy and merge keys, along with the
+    // position of the MappingEnd.
+    fn scan_mapping(&mut self) -> Result<(Vec<MappingEntry>, usize)> {
+        let mut entries: Vec<Option<MappingEntry>> = Vec::new();
+        let mut index = HashMap::new();
+        let mut merges = Vec::new();
+        loop {
+            let (event, mark) = self.peek_event_mark()?;
+            match event {
+                Event::MappingEnd | Event::Void => break,
+                Event::Scalar(scalar) if self.options.merge_keys && is_merge_key(scalar) => {
+                    *self.pos += 1;
+                    self.merge_sources(&mut merges)?;
+                    continue;
+                }
+                _ => {}
+            }
+            let pos = *self.pos;
+            let key = Value::deserialize(&mut *self)?;
+            self.ignore_any()?;
+            let entry = MappingEntry {
+                key: key.clone(),
+                pos,
+                mark,
+            };
+            match index.entry(key) {
+                hash_map::Entry::Vacant(vacant) => {
+                    vacant.insert(entries.len());
+                    entries.push(Some(entry));
+                }
+                hash_map::Entry::Occupied(mut occupied) => match self.options.duplicate_keys {
+                    None => entries.push(Some(entry)),
+                    Some(DuplicateKeyPolicy::Error) => {
+                        let first = entries[*occupied.get()].as_ref().unwrap().mark;
+                        let end = self.node_end(entry.pos);
+                        return Err(error::duplicate_key(entry.key, first, mark, end, self.path));
+                    }
+                    Some(DuplicateKeyPolicy::FirstWins) => {}
+                    Some(DuplicateKeyPolicy::LastWins) => {
+                        entries[*occupied.get()] = None;
+                        occupied.insert(entries.len());
+                        entries.push(Some(entry));
+                    }
+                },
+            }
+        }
+        let end = *self.pos;
+
+        // Keys written out explicitly take precedence over merged ones, and
+        // earlier merged mappings take precedence over later ones.
+        for source in merges {
+            *self.pos = source;
+            let mark = self.document.events[source - 1].1;
+            let (merged, _) = self.recursion_check(mark, Self::scan_mapping)?;
+            for entry in merged {
+                if let hash_map::Entry::Vacant(vacant) = index.entry(entry.key.clone()) {
+                    vacant.insert(entries.len());
+                    entries.push(Some(entry));
+                }
+            }
+        }
+
+        Ok((entries.into_iter().flatten().collect(), end))
+    }
+
+    // Consumes the value of a `<<` entry, collecting the position just past
+    // the MappingStart of each mapping to be merged.
+    fn merge_sources(&mut self, sources: &mut Vec<usize>) -> Result<()> {
+        let value = *self.pos;
+        self.ignore_any()?;
+        match self.merge_node(value)? {
+            (Event::MappingStart(_), pos) => sources.push(pos + 1),
+            (Event::SequenceStart(_), pos) => {
+                let end = *self.pos;
+                *self.pos = pos + 1;
+                while let Event::Scalar(_)
+                | Event::Alias(_)
+                | Event::SequenceStart(_)
+                | Event::MappingStart(_) = self.peek_event()?
+                {
+                    let element = *self.pos;
+                    self.ignore_any()?;
+                    match self.merge_node(element)? {
+                        (Event::MappingStart(_), pos) => sources.push(pos + 1),
+                        (Event::SequenceStart(_), _) => {
+                            return Err(error::new(ErrorImpl::SequenceInMergeElement));
+                        }
+                        _ => return Err(error::new(ErrorImpl::ScalarInMergeElement)),
+                    }
+                }
+                *self.pos = end;
+            }
+            _ => return Err(error::new(ErrorImpl::ScalarInMerge)),
+        }
+        Ok(())
+    }
+
+    // Resolves the node at `pos`, which has just been consumed, through an
+    // alias if necessary.
+    fn merge_node(&mut self, pos: usize) -> Result<(&'document Event<'de>, usize)> {
+        let pos = match &self.document.events[pos].0 {
+            Event::Alias(id) => self.alias_target(*id)?,
+            _ => pos,
+        };
+        let event = &self.document.events[pos].0;
+        let tag = match event {
+            Event::Scalar(scalar) => &scalar.tag,
+            Event::SequenceStart(sequence_start) => &sequence_start.tag,
+            Event::MappingStart(mapping_start) => &mapping_start.tag,
+            _ => &None,
+        };
+        if parse_tag(tag).is_some() {
+            return Err(error::new(ErrorImpl::TaggedInMerge));
+        }
+        Ok((event, pos))
+    }
+
     fn end_sequence(&mut self, len: usize) -> Result<()> {
         let total = {
             let mut seq = SeqAccess {
@@ -597,13 +1364,15 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
         }
     }
 
-    fn end_mapping(&mut self, len: usize) -> Result<()> {
+    fn end_mapping(&mut self, len: usize, entries: Option<&MappingEntries>) -> Result<()> {
         let total = {
             let mut map = MapAccess {
                 empty: false,
                 de: self,
                 len,
                 key: None,
+                key_mark: None,
+                entries,
             };
             while de::MapAccess::next_entry::<IgnoredAny, IgnoredAny>(&mut map)?.is_some() {}
             map.len
@@ -629,6 +1398,27 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
         }
     }
 
+    fn visit_spanned<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // Find where the node ends by skipping over it, then go back to
+        // deserialize it for real.
+        let (_event, start) = self.peek_event_mark()?;
+        let pos = *self.pos;
+        let current_enum = self.current_enum;
+        self.ignore_any()?;
+        let (_event, _start, end) = &self.document.events[*self.pos - 1];
+        *self.pos = pos;
+        self.current_enum = current_enum;
+        visitor.visit_map(SpannedAccess {
+            de: self,
+            start,
+            end: *end,
+            field: 0,
+        })
+    }
+
     fn recursion_check<F: FnOnce(&mut Self) -> Result<T>, T>(
         &mut self,
         mark: Mark,
@@ -661,22 +1451,37 @@ impl<'de, 'document, 'seq> de::SeqAccess<'de> for SeqAccess<'de, 'document, 'seq
         if self.empty {
             return Ok(None);
         }
-        match self.de.peek_event()? {
-            Event::SequenceEnd | Event::Void => Ok(None),
-            _ => {
-                let mut element_de = DeserializerFromEvents {
-                    document: self.de.document,
-                    pos: self.de.pos,
-                    jumpcount: self.de.jumpcount,
-                    path: Path::Seq {
-                        parent: &self.de.path,
-                        index: self.len,
-                    },
-                    remaining_depth: self.de.remaining_depth,
-                    current_enum: None,
-                };
-                self.len += 1;
-                seed.deserialize(&mut element_de).map(Some)
+        loop {
+            match self.de.peek_event()? {
+                Event::SequenceEnd | Event::Void => return Ok(None),
+                _ => {
+                    if self.de.skip_masked_element()? {
+                        self.len += 1;
+                        continue;
+                    }
+                    let pos = *self.de.pos;
+                    let mut element_de = DeserializerFromEvents {
+                        document: self.de.document,
+                        options: self.de.options,
+                        pos: self.de.pos,
+                        jumpcount: self.de.jumpcount,
+                        path: Path::Seq {
+                            parent: &self.de.path,
+                            index: self.len,
+                        },
+                        remaining_depth: self.de.remaining_depth,
+                        current_enum: None,
+                        recovery: self.de.recovery,
+                        ignored_key: self.de.ignored_key,
+                        entry_key: None,
+                    };
+                    self.len += 1;
+                    let result = seed.deserialize(&mut element_de);
+                    if let Some(recovery) = self.de.recovery {
+                        recovery.observe(pos, &result);
+                    }
+                    return result.map(Some);
+                }
             }
         }
     }
@@ -687,6 +1492,23 @@ struct MapAccess<'de, 'document, 'map> {
     de: &'map mut DeserializerFromEvents<'de, 'document>,
     len: usize,
     key: Option<&'document [u8]>,
+    key_mark: Option<Mark>,
+    entries: Option<&'map MappingEntries>,
+}
+
+/// The entries of a mapping that remain after applying a DuplicateKeyPolicy
+/// and merge keys.
+struct MappingEntries {
+    /// Position of the first event of each key.
+    keys: Vec<usize>,
+    /// Position of the MappingEnd event.
+    end: usize,
+}
+
+struct MappingEntry {
+    key: Value,
+    pos: usize,
+    mark: Mark,
 }
 
 impl<'de, 'document, 'map> de::MapAccess<'de> for MapAccess<'de, 'document, 'map> {
@@ -699,18 +1521,32 @@ impl<'de, 'document, 'map> de::MapAccess<'de> for MapAccess<'de, 'document, 'map
         if self.empty {
             return Ok(None);
         }
-        match self.de.peek_event()? {
-            Event::MappingEnd | Event::Void => Ok(None),
-            Event::Scalar(scalar) => {
-                self.len += 1;
-                self.key = Some(&scalar.value);
-                seed.deserialize(&mut *self.de).map(Some)
+        loop {
+            if let Some(entries) = self.entries {
+                // Skip over any entries excluded by the duplicate key policy,
+                // and continue into merged mappings.
+                *self.de.pos = entries.keys.get(self.len).copied().unwrap_or(entries.end);
+            }
+            let (event, mark) = self.de.peek_event_mark()?;
+            if let Event::MappingEnd | Event::Void = event {
+                return Ok(None);
             }
-            _ => {
+            if self.de.skip_masked_entry()? {
                 self.len += 1;
-                self.key = None;
-                seed.deserialize(&mut *self.de).map(Some)
+                continue;
             }
+            self.len += 1;
+            self.key = match event {
+                Event::Scalar(scalar) => Some(&scalar.value),
+                _ => None,
+            };
+            self.key_mark = Some(mark);
+            let pos = *self.de.pos;
+            let result = seed.deserialize(&mut *self.de);
+            if let Some(recovery) = self.de.recovery {
+                recovery.observe(pos, &result);
+            }
+            return result.map(Some);
         }
     }
 
@@ -720,6 +1556,7 @@ impl<'de, 'document, 'map> de::MapAccess<'de> for MapAccess<'de, 'document, 'map
     {
         let mut value_de = DeserializerFromEvents {
             document: self.de.document,
+            options: self.de.options,
             pos: self.de.pos,
             jumpcount: self.de.jumpcount,
             path: if let Some(key) = self.key.and_then(|key| str::from_utf8(key).ok()) {
@@ -734,8 +1571,55 @@ impl<'de, 'document, 'map> de::MapAccess<'de> for MapAccess<'de, 'document, 'map
             },
             remaining_depth: self.de.remaining_depth,
             current_enum: None,
+            recovery: self.de.recovery,
+            ignored_key: self.de.ignored_key,
+            entry_key: self.key_mark,
         };
-        seed.deserialize(&mut value_de)
+        let pos = *value_de.pos;
+        let result = seed.deserialize(&mut value_de);
+        if let Some(recovery) = self.de.recovery {
+            recovery.observe(pos, &result);
+        }
+        result
+    }
+}
+
+struct SpannedAccess<'de, 'document, 'spanned> {
+    de: &'spanned mut DeserializerFromEvents<'de, 'document>,
+    start: Mark,
+    end: Mark,
+    field: usize,
+}
+
+impl<'de, 'document, 'spanned> de::MapAccess<'de> for SpannedAccess<'de, 'document, 'spanned> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match spanned::FIELDS.get(self.field) {
+            Some(field) => seed
+                .deserialize(BorrowedStrDeserializer::<Error>::new(field))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let field = spanned::FIELDS[self.field];
+        self.field += 1;
+        let mark = match field {
+            spanned::START => self.start,
+            spanned::END => self.end,
+            _ => return seed.deserialize(&mut *self.de),
+        };
+        let location = Location::from_mark(mark);
+        let triple = [location.index(), location.line(), location.column()];
+        seed.deserialize(SeqDeserializer::<_, Error>::new(triple.into_iter()))
     }
 }
 
@@ -743,6 +1627,7 @@ struct EnumAccess<'de, 'document, 'variant> {
     de: &'variant mut DeserializerFromEvents<'de, 'document>,
     name: Option<&'static str>,
     tag: &'document str,
+    is_anchor: bool,
 }
 
 impl<'de, 'document, 'variant> de::EnumAccess<'de> for EnumAccess<'de, 'document, 'variant> {
@@ -757,6 +1642,7 @@ impl<'de, 'document, 'variant> de::EnumAccess<'de> for EnumAccess<'de, 'document
         let variant = seed.deserialize(str_de)?;
         let visitor = DeserializerFromEvents {
             document: self.de.document,
+            options: self.de.options,
             pos: self.de.pos,
             jumpcount: self.de.jumpcount,
             path: self.de.path,
@@ -764,7 +1650,11 @@ impl<'de, 'document, 'variant> de::EnumAccess<'de> for EnumAccess<'de, 'document
             current_enum: Some(CurrentEnum {
                 name: self.name,
                 tag: self.tag,
+                is_anchor: self.is_anchor,
             }),
+            recovery: self.de.recovery,
+            ignored_key: self.de.ignored_key,
+            entry_key: None,
         };
         Ok((variant, visitor))
     }
@@ -855,7 +1745,12 @@ impl<'de, 'document, 'variant> de::VariantAccess<'de>
     }
 }
 
-fn visit_scalar<'de, V>(visitor: V, scalar: &Scalar<'de>, tagged_already: bool) -> Result<V::Value>
+fn visit_scalar<'de, V>(
+    visitor: V,
+    scalar: &Scalar<'de>,
+    tagged_already: bool,
+    schema: Schema,
+) -> Result<V::Value>
 where
     V: Visitor<'de>,
 {
@@ -868,32 +1763,38 @@ where
             ))
         }
     };
+    if is_timestamp(scalar) && parse_timestamp(v).is_none() {
+        return Err(de::Error::invalid_value(
+            Unexpected::Str(v),
+            &"a YAML timestamp",
+        ));
+    }
     if let (Some(tag), false) = (&scalar.tag, tagged_already) {
         if tag == Tag::BOOL {
-            return match parse_bool(v) {
+            return match parse_bool(v, schema) {
                 Some(v) => visitor.visit_bool(v),
                 None => Err(de::Error::invalid_value(Unexpected::Str(v), &"a boolean")),
             };
         } else if tag == Tag::INT {
-            return match visit_int(visitor, v) {
+            return match visit_int(visitor, v, schema) {
                 Ok(result) => result,
                 Err(_) => Err(de::Error::invalid_value(Unexpected::Str(v), &"an integer")),
             };
         } else if tag == Tag::FLOAT {
-            return match parse_f64(v) {
+            return match parse_f64(v, schema) {
                 Some(v) => visitor.visit_f64(v),
                 None => Err(de::Error::invalid_value(Unexpected::Str(v), &"a float")),
             };
         } else if tag == Tag::NULL {
-            return match parse_null(v.as_bytes()) {
+            return match parse_null(v.as_bytes(), schema) {
                 Some(()) => visitor.visit_unit(),
                 None => Err(de::Error::invalid_value(Unexpected::Str(v), &"null")),
             };
         } else if tag.starts_with("!") && scalar.style == ScalarStyle::Plain {
-            return visit_untagged_scalar(visitor, v, scalar.repr, scalar.style);
+            return visit_untagged_scalar(visitor, v, scalar.repr, scalar.style, schema);
         }
-    } else if scalar.style == ScalarStyle::Plain {
-        return visit_untagged_scalar(visitor, v, scalar.repr, scalar.style);
+    } else if scalar.style == ScalarStyle::Plain && !is_binary(scalar) && !is_timestamp(scalar) {
+        return visit_untagged_scalar(visitor, v, scalar.repr, scalar.style, schema);
     }
     if let Some(borrowed) = parse_borrowed_str(v, scalar.repr, scalar.style) {
         visitor.visit_borrowed_str(borrowed)
@@ -902,7 +1803,7 @@ where
     }
 }
 
-fn parse_borrowed_str<'de>(
+pub(crate) fn parse_borrowed_str<'de>(
     utf8_value: &str,
     repr: Option<&'de [u8]>,
     style: ScalarStyle,
@@ -922,17 +1823,25 @@ fn parse_borrowed_str<'de>(
     None
 }
 
-fn parse_null(scalar: &[u8]) -> Option<()> {
-    match scalar {
-        b"null" | b"Null" | b"NULL" | b"~" => Some(()),
+fn parse_null(scalar: &[u8], schema: Schema) -> Option<()> {
+    match (scalar, schema) {
+        (b"null", _) => Some(()),
+        (b"Null" | b"NULL" | b"~", Schema::Core | Schema::Yaml11) => Some(()),
         _ => None,
     }
 }
 
-fn parse_bool(scalar: &str) -> Option<bool> {
-    match scalar {
-        "true" | "True" | "TRUE" => Some(true),
-        "false" | "False" | "FALSE" => Some(false),
+fn parse_bool(scalar: &str, schema: Schema) -> Option<bool> {
+    match (scalar, schema) {
+        ("true", _) => Some(true),
+        ("false", _) => Some(false),
+        ("True" | "TRUE", Schema::Core | Schema::Yaml11) => Some(true),
+        ("False" | "FALSE", Schema::Core | Schema::Yaml11) => Some(false),
+        // PyYAML leaves out the single letter forms `y` and `n` allowed by the
+        // YAML 1.1 type repository, and so do we, as they are much more likely
+        // to be meant as strings.
+        ("yes" | "Yes" | "YES" | "on" | "On" | "ON", Schema::Yaml11) => Some(true),
+        ("no" | "No" | "NO" | "off" | "Off" | "OFF", Schema::Yaml11) => Some(false),
         _ => None,
     }
 }
@@ -940,7 +1849,13 @@ fn parse_bool(scalar: &str) -> Option<bool> {
 fn parse_unsigned_int<T>(
     scalar: &str,
     from_str_radix: fn(&str, radix: u32) -> Result<T, ParseIntError>,
+    schema: Schema,
 ) -> Option<T> {
+    match schema {
+        Schema::Core => {}
+        Schema::Json => return parse_json_int(scalar, from_str_radix),
+        Schema::Yaml11 => return parse_yaml11_int(scalar, from_str_radix),
+    }
     let unpositive = scalar.strip_prefix('+').unwrap_or(scalar);
     if let Some(rest) = unpositive.strip_prefix("0x") {
         if rest.starts_with(['+', '-']) {
@@ -978,7 +1893,13 @@ fn parse_unsigned_int<T>(
 fn parse_signed_int<T>(
     scalar: &str,
     from_str_radix: fn(&str, radix: u32) -> Result<T, ParseIntError>,
+    schema: Schema,
 ) -> Option<T> {
+    match schema {
+        Schema::Core => {}
+        Schema::Json => return parse_json_int(scalar, from_str_radix),
+        Schema::Yaml11 => return parse_yaml11_int(scalar, from_str_radix),
+    }
     let unpositive = if let Some(unpositive) = scalar.strip_prefix('+') {
         if unpositive.starts_with(['+', '-']) {
             return None;
@@ -1038,7 +1959,13 @@ fn parse_signed_int<T>(
 fn parse_negative_int<T>(
     scalar: &str,
     from_str_radix: fn(&str, radix: u32) -> Result<T, ParseIntError>,
+    schema: Schema,
 ) -> Option<T> {
+    match schema {
+        Schema::Core => {}
+        Schema::Json => return parse_json_int(scalar, from_str_radix),
+        Schema::Yaml11 => return parse_yaml11_int(scalar, from_str_radix),
+    }
     if let Some(rest) = scalar.strip_prefix("-0x") {
         let negative = format!("-{}", rest);
         if let Ok(int) = from_str_radix(&negative, 16) {
@@ -1063,7 +1990,80 @@ fn parse_negative_int<T>(
     from_str_radix(scalar, 10).ok()
 }
 
-pub(crate) fn parse_f64(scalar: &str) -> Option<f64> {
+// `-?(0|[1-9][0-9]*)`
+fn parse_json_int<T>(
+    scalar: &str,
+    from_str_radix: fn(&str, radix: u32) -> Result<T, ParseIntError>,
+) -> Option<T> {
+    let digits = scalar.strip_prefix('-').unwrap_or(scalar);
+    if digits.is_empty()
+        || !digits.bytes().all(|b| b.is_ascii_digit())
+        || digits.len() > 1 && digits.starts_with('0')
+    {
+        return None;
+    }
+    from_str_radix(scalar, 10).ok()
+}
+
+// `[-+]?0b[0-1_]+`, `[-+]?0[0-7_]+`, `[-+]?(0|[1-9][0-9_]*)`,
+// `[-+]?0x[0-9a-fA-F_]+` and `[-+]?[1-9][0-9_]*(:[0-5]?[0-9])+`
+fn parse_yaml11_int<T>(
+    scalar: &str,
+    from_str_radix: fn(&str, radix: u32) -> Result<T, ParseIntError>,
+) -> Option<T> {
+    let (sign, unsigned) = if let Some(rest) = scalar.strip_prefix('-') {
+        ("-", rest)
+    } else {
+        ("", scalar.strip_prefix('+').unwrap_or(scalar))
+    };
+    let (radix, digits) = if let Some(rest) = unsigned.strip_prefix("0b") {
+        (2, rest)
+    } else if let Some(rest) = unsigned.strip_prefix("0x") {
+        (16, rest)
+    } else if unsigned.contains(':') {
+        let sexagesimal = parse_sexagesimal(unsigned)?;
+        return from_str_radix(&format!("{}{}", sign, sexagesimal), 10).ok();
+    } else if unsigned.len() > 1 && unsigned.starts_with('0') {
+        (8, &unsigned[1..])
+    } else if unsigned.starts_with(|ch: char| ch.is_ascii_digit()) {
+        (10, unsigned)
+    } else {
+        return None;
+    };
+    let digits = digits.replace('_', "");
+    if digits.is_empty() || !digits.chars().all(|ch| ch.is_digit(radix)) {
+        return None;
+    }
+    from_str_radix(&format!("{}{}", sign, digits), radix).ok()
+}
+
+// `[1-9][0-9_]*(:[0-5]?[0-9])+`
+fn parse_sexagesimal(scalar: &str) -> Option<u128> {
+    let mut parts = scalar.split(':');
+    let first = parts.next()?.replace('_', "");
+    if !first.starts_with(['1', '2', '3', '4', '5', '6', '7', '8', '9'])
+        || !first.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let mut value = first.parse::<u128>().ok()?;
+    for part in parts {
+        let digit = match part.as_bytes() {
+            [ones @ b'0'..=b'9'] => ones - b'0',
+            [tens @ b'0'..=b'5', ones @ b'0'..=b'9'] => (tens - b'0') * 10 + (ones - b'0'),
+            _ => return None,
+        };
+        value = value.checked_mul(60)?.checked_add(u128::from(digit))?;
+    }
+    Some(value)
+}
+
+pub(crate) fn parse_f64(scalar: &str, schema: Schema) -> Option<f64> {
+    match schema {
+        Schema::Core => {}
+        Schema::Json => return parse_json_f64(scalar),
+        Schema::Yaml11 => return parse_yaml11_f64(scalar),
+    }
     let unpositive = if let Some(unpositive) = scalar.strip_prefix('+') {
         if unpositive.starts_with(['+', '-']) {
             return None;
@@ -1089,6 +2089,235 @@ pub(crate) fn parse_f64(scalar: &str) -> Option<f64> {
     None
 }
 
+// `-?(0|[1-9][0-9]*)(\.[0-9]*)?([eE][-+]?[0-9]+)?`
+fn parse_json_f64(scalar: &str) -> Option<f64> {
+    let unsigned = scalar.strip_prefix('-').unwrap_or(scalar);
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(i) => (&unsigned[..i], Some(&unsigned[i + 1..])),
+        None => (unsigned, None),
+    };
+    let (int, fraction) = match mantissa.split_once('.') {
+        Some((int, fraction)) => (int, fraction),
+        None => (mantissa, ""),
+    };
+    if int.is_empty()
+        || !int.bytes().all(|b| b.is_ascii_digit())
+        || int.len() > 1 && int.starts_with('0')
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    if let Some(exponent) = exponent {
+        let digits = exponent.strip_prefix(['-', '+']).unwrap_or(exponent);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+    }
+    scalar.parse::<f64>().ok().filter(|float| float.is_finite())
+}
+
+// `[-+]?([0-9][0-9_]*)?\.[0-9_]*([eE][-+][0-9]+)?`,
+// `[-+]?[0-9][0-9_]*(:[0-5]?[0-9])+\.[0-9_]*`, `[-+]?\.(inf|Inf|INF)` and
+// `\.(nan|NaN|NAN)`
+fn parse_yaml11_f64(scalar: &str) -> Option<f64> {
+    if let ".nan" | ".NaN" | ".NAN" = scalar {
+        return Some(f64::NAN.copysign(1.0));
+    }
+    let (negative, unsigned) = if let Some(rest) = scalar.strip_prefix('-') {
+        (true, rest)
+    } else {
+        (false, scalar.strip_prefix('+').unwrap_or(scalar))
+    };
+    let float = if let ".inf" | ".Inf" | ".INF" = unsigned {
+        f64::INFINITY
+    } else if let Some((int, fraction)) = unsigned.split_once('.') {
+        if !int.is_empty() && !int.starts_with(|ch: char| ch.is_ascii_digit()) {
+            return None;
+        }
+        let (fraction, exponent) = match fraction.find(['e', 'E']) {
+            Some(i) => (&fraction[..i], Some(&fraction[i + 1..])),
+            None => (fraction, None),
+        };
+        if int.is_empty() && !fraction.starts_with(|ch: char| ch.is_ascii_digit()) {
+            return None;
+        }
+        let fraction = fraction.replace('_', "");
+        if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        if let Some(exponent) = exponent {
+            let digits = exponent.strip_prefix(['-', '+'])?;
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+        }
+        if int.contains(':') {
+            if exponent.is_some() {
+                return None;
+            }
+            let int = parse_sexagesimal(int)? as f64;
+            int + format!("0.{}", fraction).parse::<f64>().ok()?
+        } else {
+            let int = int.replace('_', "");
+            if !int.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let mut float = format!("{}.{}", int, fraction);
+            if let Some(exponent) = exponent {
+                float.push('e');
+                float.push_str(exponent);
+            }
+            float
+                .parse::<f64>()
+                .ok()
+                .filter(|float| float.is_finite())?
+        }
+    } else {
+        return None;
+    };
+    Some(if negative { -float } else { float })
+}
+
+// https://yaml.org/type/timestamp.html
+//
+//     [0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]
+//   | [0-9][0-9][0-9][0-9]-[0-9][0-9]?-[0-9][0-9]?
+//     ([Tt]|[ \t]+)[0-9][0-9]?:[0-9][0-9]:[0-9][0-9](\.[0-9]*)?
+//     (([ \t]*)Z|[-+][0-9][0-9]?(:[0-9][0-9])?)?
+//
+// As in other implementations, whitespace is also allowed before a numeric
+// time zone, as in the spec's own example `2001-12-14 21:59:43.10 -5`.
+pub(crate) fn parse_timestamp(scalar: &str) -> Option<Timestamp> {
+    fn digits(bytes: &mut &[u8], min: usize, max: usize) -> Option<u32> {
+        let len = bytes
+            .iter()
+            .take(max)
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if len < min {
+            return None;
+        }
+        let (number, rest) = bytes.split_at(len);
+        *bytes = rest;
+        Some(number.iter().fold(0, |n, b| n * 10 + u32::from(b - b'0')))
+    }
+
+    fn punct(bytes: &mut &[u8], ch: u8) -> Option<()> {
+        let (first, rest) = bytes.split_first()?;
+        if *first != ch {
+            return None;
+        }
+        *bytes = rest;
+        Some(())
+    }
+
+    fn skip_blanks(bytes: &mut &[u8]) -> usize {
+        let len = bytes
+            .iter()
+            .take_while(|&&b| b == b' ' || b == b'\t')
+            .count();
+        *bytes = &bytes[len..];
+        len
+    }
+
+    let mut bytes = scalar.as_bytes();
+    let year = digits(&mut bytes, 4, 4)?;
+    punct(&mut bytes, b'-')?;
+    let month = digits(&mut bytes, 1, 2)?;
+    punct(&mut bytes, b'-')?;
+    let day = digits(&mut bytes, 1, 2)?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let date_len = scalar.len() - bytes.len();
+    if bytes.is_empty() {
+        // The date-only form requires two-digit month and day.
+        return if date_len == 10 {
+            Some(Timestamp {
+                year: year as u16,
+                month: month as u8,
+                day: day as u8,
+                time: None,
+            })
+        } else {
+            None
+        };
+    }
+
+    if let Some(b'T' | b't') = bytes.first() {
+        bytes = &bytes[1..];
+    } else if skip_blanks(&mut bytes) == 0 {
+        return None;
+    }
+    let hour = digits(&mut bytes, 1, 2)?;
+    punct(&mut bytes, b':')?;
+    let minute = digits(&mut bytes, 2, 2)?;
+    punct(&mut bytes, b':')?;
+    let second = digits(&mut bytes, 2, 2)?;
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    let mut nanosecond = 0;
+    if punct(&mut bytes, b'.').is_some() {
+        let len = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+        let (fraction, rest) = bytes.split_at(len);
+        for i in 0..9 {
+            let digit = fraction.get(i).map_or(0, |b| u32::from(b - b'0'));
+            nanosecond = nanosecond * 10 + digit;
+        }
+        bytes = rest;
+    }
+
+    skip_blanks(&mut bytes);
+    let utc_offset = match bytes.first() {
+        None => None,
+        Some(b'Z') => {
+            bytes = &bytes[1..];
+            Some(0)
+        }
+        Some(&sign @ (b'+' | b'-')) => {
+            bytes = &bytes[1..];
+            let hours = digits(&mut bytes, 1, 2)?;
+            let minutes = if punct(&mut bytes, b':').is_some() {
+                digits(&mut bytes, 2, 2)?
+            } else {
+                0
+            };
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = (hours * 60 + minutes) as i32 * 60;
+            Some(if sign == b'-' { -offset } else { offset })
+        }
+        Some(_) => return None,
+    };
+    if !bytes.is_empty() {
+        return None;
+    }
+
+    Some(Timestamp {
+        year: year as u16,
+        month: month as u8,
+        day: day as u8,
+        time: Some(Time {
+            hour: hour as u8,
+            minute: minute as u8,
+            second: second as u8,
+            nanosecond,
+            utc_offset,
+        }),
+    })
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
 pub(crate) fn digits_but_not_number(scalar: &str) -> bool {
     // Leading zero(s) followed by numeric characters is a string according to
     // the YAML 1.2 spec. https://yaml.org/spec/1.2/spec.html#id2761292
@@ -1096,20 +2325,20 @@ pub(crate) fn digits_but_not_number(scalar: &str) -> bool {
     scalar.len() > 1 && scalar.starts_with('0') && scalar[1..].bytes().all(|b| b.is_ascii_digit())
 }
 
-pub(crate) fn visit_int<'de, V>(visitor: V, v: &str) -> Result<Result<V::Value>, V>
+pub(crate) fn visit_int<'de, V>(visitor: V, v: &str, schema: Schema) -> Result<Result<V::Value>, V>
 where
     V: Visitor<'de>,
 {
-    if let Some(int) = parse_unsigned_int(v, u64::from_str_radix) {
+    if let Some(int) = parse_unsigned_int(v, u64::from_str_radix, schema) {
         return Ok(visitor.visit_u64(int));
     }
-    if let Some(int) = parse_negative_int(v, i64::from_str_radix) {
+    if let Some(int) = parse_negative_int(v, i64::from_str_radix, schema) {
         return Ok(visitor.visit_i64(int));
     }
-    if let Some(int) = parse_unsigned_int(v, u128::from_str_radix) {
+    if let Some(int) = parse_unsigned_int(v, u128::from_str_radix, schema) {
         return Ok(visitor.visit_u128(int));
     }
-    if let Some(int) = parse_negative_int(v, i128::from_str_radix) {
+    if let Some(int) = parse_negative_int(v, i128::from_str_radix, schema) {
         return Ok(visitor.visit_i128(int));
     }
     Err(visitor)
@@ -1120,22 +2349,23 @@ pub(crate) fn visit_untagged_scalar<'de, V>(
     v: &str,
     repr: Option<&'de [u8]>,
     style: ScalarStyle,
+    schema: Schema,
 ) -> Result<V::Value>
 where
     V: Visitor<'de>,
 {
-    if v.is_empty() || parse_null(v.as_bytes()) == Some(()) {
+    if v.is_empty() || parse_null(v.as_bytes(), schema) == Some(()) {
         return visitor.visit_unit();
     }
-    if let Some(boolean) = parse_bool(v) {
+    if let Some(boolean) = parse_bool(v, schema) {
         return visitor.visit_bool(boolean);
     }
-    let visitor = match visit_int(visitor, v) {
+    let visitor = match visit_int(visitor, v, schema) {
         Ok(result) => return result,
         Err(visitor) => visitor,
     };
     if !digits_but_not_number(v) {
-        if let Some(float) = parse_f64(v) {
+        if let Some(float) = parse_f64(v, schema) {
             return visitor.visit_f64(float);
         }
     }
@@ -1158,7 +2388,7 @@ fn is_plain_or_tagged_literal_scalar(
     }
 }
 
-fn invalid_type(event: &Event, exp: &dyn Expected) -> Error {
+fn invalid_type(event: &Event, exp: &dyn Expected, schema: Schema) -> Error {
     enum Void {}
 
     struct InvalidType<'a> {
@@ -1177,7 +2407,7 @@ fn invalid_type(event: &Event, exp: &dyn Expected) -> Error {
         Event::Alias(_) => unreachable!(),
         Event::Scalar(scalar) => {
             let get_type = InvalidType { exp };
-            match visit_scalar(get_type, scalar, false) {
+            match visit_scalar(get_type, scalar, false, schema) {
                 Ok(void) => match void {},
                 Err(invalid_type) => invalid_type,
             }
@@ -1190,8 +2420,46 @@ fn invalid_type(event: &Event, exp: &dyn Expected) -> Error {
     }
 }
 
+fn is_merge_key(scalar: &Scalar) -> bool {
+    match &scalar.tag {
+        Some(tag) => tag == Tag::MERGE,
+        None => scalar.style == ScalarStyle::Plain && *scalar.value == *b"<<",
+    }
+}
+
+fn is_binary(scalar: &Scalar) -> bool {
+    match &scalar.tag {
+        Some(tag) => tag == Tag::BINARY,
+        None => false,
+    }
+}
+
+fn is_timestamp(scalar: &Scalar) -> bool {
+    match &scalar.tag {
+        Some(tag) => tag == Tag::TIMESTAMP,
+        None => false,
+    }
+}
+
+fn parse_binary(scalar: &Scalar) -> Result<Vec<u8>> {
+    let exp = &"base64-encoded binary data";
+    match str::from_utf8(&scalar.value) {
+        Ok(v) => base64::decode(v).ok_or_else(|| de::Error::invalid_value(Unexpected::Str(v), exp)),
+        Err(_) => Err(de::Error::invalid_value(
+            Unexpected::Bytes(&scalar.value),
+            exp,
+        )),
+    }
+}
+
 fn parse_tag(libyaml_tag: &Option<Tag>) -> Option<&str> {
     let mut bytes: &[u8] = libyaml_tag.as_ref()?;
+    if *bytes == *Tag::BINARY.as_bytes() {
+        return Some(value::tagged::BINARY);
+    }
+    if *bytes == *Tag::TIMESTAMP.as_bytes() {
+        return Some(value::tagged::TIMESTAMP);
+    }
     if let (b'!', rest) = bytes.split_first()? {
         if !rest.is_empty() {
             bytes = rest;
@@ -1209,7 +2477,13 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
-        let tagged_already = self.current_enum.is_some();
+        // An anchor is reported like a tag, outside of the node's own tag.
+        let anchored_already = self.current_enum.is_some();
+        let tagged_already = match self.current_enum {
+            Some(current_enum) => !current_enum.is_anchor,
+            None => false,
+        };
+        let node = *self.pos;
         let (next, mark) = self.next_event_mark()?;
         fn enum_tag(tag: &Option<Tag>, tagged_already: bool) -> Option<&str> {
             if tagged_already {
@@ -1217,8 +2491,19 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
             }
             parse_tag(tag)
         }
+        let anchor_name = self.document.anchor_names.get(&(*self.pos - 1));
         loop {
+            if let (Some(anchor_name), false) = (anchor_name, anchored_already) {
+                *self.pos -= 1;
+                break visitor.visit_enum(EnumAccess {
+                    de: self,
+                    name: None,
+                    tag: anchor_name,
+                    is_anchor: true,
+                });
+            }
             match next {
+                Event::Alias(_) if anchor_name.is_some() => break visitor.visit_unit(),
                 Event::Alias(mut pos) => break self.jump(&mut pos)?.deserialize_any(visitor),
                 Event::Scalar(scalar) => {
                     if let Some(tag) = enum_tag(&scalar.tag, tagged_already) {
@@ -1227,9 +2512,10 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                             de: self,
                             name: None,
                             tag,
+                            is_anchor: false,
                         });
                     }
-                    break visit_scalar(visitor, scalar, tagged_already);
+                    break visit_scalar(visitor, scalar, tagged_already, self.options.schema);
                 }
                 Event::SequenceStart(sequence) => {
                     if let Some(tag) = enum_tag(&sequence.tag, tagged_already) {
@@ -1238,6 +2524,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                             de: self,
                             name: None,
                             tag,
+                            is_anchor: false,
                         });
                     }
                     break self.visit_sequence(visitor, mark);
@@ -1249,6 +2536,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                             de: self,
                             name: None,
                             tag,
+                            is_anchor: false,
                         });
                     }
                     break self.visit_mapping(visitor, mark);
@@ -1260,7 +2548,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         }
         // The de::Error impl creates errors with unknown line and column. Fill
         // in the position here by looking at the current index in the input.
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, || self.node_end(node), self.path))
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
@@ -1268,6 +2556,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         V: Visitor<'de>,
     {
         let tagged_already = self.current_enum.is_some();
+        let node = *self.pos;
         let (next, mark) = self.next_event_mark()?;
         loop {
             match next {
@@ -1276,16 +2565,16 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                     if is_plain_or_tagged_literal_scalar(Tag::BOOL, scalar, tagged_already) =>
                 {
                     if let Ok(value) = str::from_utf8(&scalar.value) {
-                        if let Some(boolean) = parse_bool(value) {
+                        if let Some(boolean) = parse_bool(value, self.options.schema) {
                             break visitor.visit_bool(boolean);
                         }
                     }
                 }
                 _ => {}
             }
-            break Err(invalid_type(next, &visitor));
+            break Err(invalid_type(next, &visitor, self.options.schema));
         }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, || self.node_end(node), self.path))
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
@@ -1314,6 +2603,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         V: Visitor<'de>,
     {
         let tagged_already = self.current_enum.is_some();
+        let node = *self.pos;
         let (next, mark) = self.next_event_mark()?;
         loop {
             match next {
@@ -1322,16 +2612,18 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                     if is_plain_or_tagged_literal_scalar(Tag::INT, scalar, tagged_already) =>
                 {
                     if let Ok(value) = str::from_utf8(&scalar.value) {
-                        if let Some(int) = parse_signed_int(value, i64::from_str_radix) {
+                        if let Some(int) =
+                            parse_signed_int(value, i64::from_str_radix, self.options.schema)
+                        {
                             break visitor.visit_i64(int);
                         }
                     }
                 }
                 _ => {}
             }
-            break Err(invalid_type(next, &visitor));
+            break Err(invalid_type(next, &visitor, self.options.schema));
         }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, || self.node_end(node), self.path))
     }
 
     fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
@@ -1339,6 +2631,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         V: Visitor<'de>,
     {
         let tagged_already = self.current_enum.is_some();
+        let node = *self.pos;
         let (next, mark) = self.next_event_mark()?;
         loop {
             match next {
@@ -1347,16 +2640,18 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                     if is_plain_or_tagged_literal_scalar(Tag::INT, scalar, tagged_already) =>
                 {
                     if let Ok(value) = str::from_utf8(&scalar.value) {
-                        if let Some(int) = parse_signed_int(value, i128::from_str_radix) {
+                        if let Some(int) =
+                            parse_signed_int(value, i128::from_str_radix, self.options.schema)
+                        {
                             break visitor.visit_i128(int);
                         }
                     }
                 }
                 _ => {}
             }
-            break Err(invalid_type(next, &visitor));
+            break Err(invalid_type(next, &visitor, self.options.schema));
         }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, || self.node_end(node), self.path))
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
@@ -1385,6 +2680,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         V: Visitor<'de>,
     {
         let tagged_already = self.current_enum.is_some();
+        let node = *self.pos;
         let (next, mark) = self.next_event_mark()?;
         loop {
             match next {
@@ -1393,16 +2689,18 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                     if is_plain_or_tagged_literal_scalar(Tag::INT, scalar, tagged_already) =>
                 {
                     if let Ok(value) = str::from_utf8(&scalar.value) {
-                        if let Some(int) = parse_unsigned_int(value, u64::from_str_radix) {
+                        if let Some(int) =
+                            parse_unsigned_int(value, u64::from_str_radix, self.options.schema)
+                        {
                             break visitor.visit_u64(int);
                         }
                     }
                 }
                 _ => {}
             }
-            break Err(invalid_type(next, &visitor));
+            break Err(invalid_type(next, &visitor, self.options.schema));
         }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, || self.node_end(node), self.path))
     }
 
     fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
@@ -1410,6 +2708,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         V: Visitor<'de>,
     {
         let tagged_already = self.current_enum.is_some();
+        let node = *self.pos;
         let (next, mark) = self.next_event_mark()?;
         loop {
             match next {
@@ -1418,16 +2717,18 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                     if is_plain_or_tagged_literal_scalar(Tag::INT, scalar, tagged_already) =>
                 {
                     if let Ok(value) = str::from_utf8(&scalar.value) {
-                        if let Some(int) = parse_unsigned_int(value, u128::from_str_radix) {
+                        if let Some(int) =
+                            parse_unsigned_int(value, u128::from_str_radix, self.options.schema)
+                        {
                             break visitor.visit_u128(int);
                         }
                     }
                 }
                 _ => {}
             }
-            break Err(invalid_type(next, &visitor));
+            break Err(invalid_type(next, &visitor, self.options.schema));
         }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, || self.node_end(node), self.path))
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
@@ -1442,6 +2743,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         V: Visitor<'de>,
     {
         let tagged_already = self.current_enum.is_some();
+        let node = *self.pos;
         let (next, mark) = self.next_event_mark()?;
         loop {
             match next {
@@ -1450,16 +2752,16 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                     if is_plain_or_tagged_literal_scalar(Tag::FLOAT, scalar, tagged_already) =>
                 {
                     if let Ok(value) = str::from_utf8(&scalar.value) {
-                        if let Some(float) = parse_f64(value) {
+                        if let Some(float) = parse_f64(value, self.options.schema) {
                             break visitor.visit_f64(float);
                         }
                     }
                 }
                 _ => {}
             }
-            break Err(invalid_type(next, &visitor));
+            break Err(invalid_type(next, &visitor, self.options.schema));
         }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, || self.node_end(node), self.path))
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
@@ -1473,6 +2775,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        let node = *self.pos;
         let (next, mark) = self.next_event_mark()?;
         match next {
             Event::Scalar(scalar) => {
@@ -1483,13 +2786,13 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                         visitor.visit_str(v)
                     }
                 } else {
-                    Err(invalid_type(next, &visitor))
+                    Err(invalid_type(next, &visitor, self.options.schema))
                 }
             }
             Event::Alias(mut pos) => self.jump(&mut pos)?.deserialize_str(visitor),
-            other => Err(invalid_type(other, &visitor)),
+            other => Err(invalid_type(other, &visitor, self.options.schema)),
         }
-        .map_err(|err: Error| error::fix_mark(err, mark, self.path))
+        .map_err(|err: Error| error::fix_mark(err, mark, || self.node_end(node), self.path))
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
@@ -1499,18 +2802,33 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         self.deserialize_str(visitor)
     }
 
-    fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value>
+    /// Decodes `!!binary` scalars from base64. Anything else is deserialized
+    /// the same way as by `deserialize_any`.
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        Err(error::new(ErrorImpl::BytesUnsupported))
+        let node = *self.pos;
+        let (next, mark) = self.peek_event_mark()?;
+        match next {
+            Event::Scalar(scalar) if is_binary(scalar) => {
+                *self.pos += 1;
+                parse_binary(scalar).and_then(|bytes| visitor.visit_byte_buf(bytes))
+            }
+            Event::Alias(mut pos) => {
+                *self.pos += 1;
+                self.jump(&mut pos)?.deserialize_bytes(visitor)
+            }
+            _ => return self.deserialize_any(visitor),
+        }
+        .map_err(|err: Error| error::fix_mark(err, mark, || self.node_end(node), self.path))
     }
 
-    fn deserialize_byte_buf<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        Err(error::new(ErrorImpl::BytesUnsupported))
+        self.deserialize_bytes(visitor)
     }
 
     /// Parses `null` as None and any other values as `Some(...)`.
@@ -1529,7 +2847,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                     true
                 } else if let (Some(tag), false) = (&scalar.tag, tagged_already) {
                     if tag == Tag::NULL {
-                        if let Some(()) = parse_null(&scalar.value) {
+                        if let Some(()) = parse_null(&scalar.value, self.options.schema) {
                             false
                         } else if let Ok(v) = str::from_utf8(&scalar.value) {
                             return Err(de::Error::invalid_value(Unexpected::Str(v), &"null"));
@@ -1543,7 +2861,8 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                         true
                     }
                 } else {
-                    !scalar.value.is_empty() && parse_null(&scalar.value).is_none()
+                    !scalar.value.is_empty()
+                        && parse_null(&scalar.value, self.options.schema).is_none()
                 }
             }
             Event::SequenceStart(_) | Event::MappingStart(_) => true,
@@ -1565,15 +2884,17 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         V: Visitor<'de>,
     {
         let tagged_already = self.current_enum.is_some();
+        let node = *self.pos;
         let (next, mark) = self.next_event_mark()?;
         match next {
             Event::Scalar(scalar) => {
                 let is_null = if scalar.style != ScalarStyle::Plain {
                     false
                 } else if let (Some(tag), false) = (&scalar.tag, tagged_already) {
-                    tag == Tag::NULL && parse_null(&scalar.value).is_some()
+                    tag == Tag::NULL && parse_null(&scalar.value, self.options.schema).is_some()
                 } else {
-                    scalar.value.is_empty() || parse_null(&scalar.value).is_some()
+                    scalar.value.is_empty()
+                        || parse_null(&scalar.value, self.options.schema).is_some()
                 };
                 if is_null {
                     visitor.visit_unit()
@@ -1588,9 +2909,9 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
             }
             Event::Alias(mut pos) => self.jump(&mut pos)?.deserialize_unit(visitor),
             Event::Void => visitor.visit_unit(),
-            other => Err(invalid_type(other, &visitor)),
+            other => Err(invalid_type(other, &visitor, self.options.schema)),
         }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, || self.node_end(node), self.path))
     }
 
     fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
@@ -1613,10 +2934,14 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        let node = *self.pos;
         let (next, mark) = self.next_event_mark()?;
         match next {
             Event::Alias(mut pos) => self.jump(&mut pos)?.deserialize_seq(visitor),
             Event::SequenceStart(_) => self.visit_sequence(visitor, mark),
+            Event::Scalar(scalar) if is_binary(scalar) => parse_binary(scalar).and_then(|bytes| {
+                visitor.visit_seq(SeqDeserializer::<_, Error>::new(bytes.into_iter()))
+            }),
             other => {
                 if match other {
                     Event::Void => true,
@@ -1631,11 +2956,11 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                         len: 0,
                     })
                 } else {
-                    Err(invalid_type(other, &visitor))
+                    Err(invalid_type(other, &visitor, self.options.schema))
                 }
             }
         }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, || self.node_end(node), self.path))
     }
 
     fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
@@ -1661,6 +2986,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        let node = *self.pos;
         let (next, mark) = self.next_event_mark()?;
         match next {
             Event::Alias(mut pos) => self.jump(&mut pos)?.deserialize_map(visitor),
@@ -1678,24 +3004,29 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                         de: self,
                         len: 0,
                         key: None,
+                        key_mark: None,
+                        entries: None,
                     })
                 } else {
-                    Err(invalid_type(other, &visitor))
+                    Err(invalid_type(other, &visitor, self.options.schema))
                 }
             }
         }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, || self.node_end(node), self.path))
     }
 
     fn deserialize_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         _fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if name == spanned::NAME {
+            return self.visit_spanned(visitor);
+        }
         self.deserialize_map(visitor)
     }
 
@@ -1711,6 +3042,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        let node = *self.pos;
         let (next, mark) = self.peek_event_mark()?;
         loop {
             if let Some(current_enum) = self.current_enum {
@@ -1744,6 +3076,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                             de: self,
                             name: Some(name),
                             tag,
+                            is_anchor: false,
                         });
                     }
                     visitor.visit_enum(UnitVariantAccess { de: self })
@@ -1754,11 +3087,17 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                             de: self,
                             name: Some(name),
                             tag,
+                            is_anchor: false,
                         });
                     }
                     let err =
                         de::Error::invalid_type(Unexpected::Map, &"a YAML tag starting with '!'");
-                    Err(error::fix_mark(err, mark, self.path))
+                    Err(error::fix_mark(
+                        err,
+                        mark,
+                        || self.node_end(node),
+                        self.path,
+                    ))
                 }
                 Event::SequenceStart(sequence) => {
                     if let Some(tag) = parse_tag(&sequence.tag) {
@@ -1766,18 +3105,24 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                             de: self,
                             name: Some(name),
                             tag,
+                            is_anchor: false,
                         });
                     }
                     let err =
                         de::Error::invalid_type(Unexpected::Seq, &"a YAML tag starting with '!'");
-                    Err(error::fix_mark(err, mark, self.path))
+                    Err(error::fix_mark(
+                        err,
+                        mark,
+                        || self.node_end(node),
+                        self.path,
+                    ))
                 }
                 Event::SequenceEnd => panic!("unexpected end of sequence"),
                 Event::MappingEnd => panic!("unexpected end of mapping"),
                 Event::Void => Err(error::new(ErrorImpl::EndOfStream)),
             };
         }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, || self.node_end(node), self.path))
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
@@ -1791,6 +3136,9 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let (Some(ignored_key), Some(mark)) = (self.ignored_key, self.entry_key) {
+            ignored_key(&self.path.to_string(), Location::from_mark(mark));
+        }
         self.ignore_any()?;
         visitor.visit_unit()
     }
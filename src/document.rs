@@ -0,0 +1,939 @@
+//! Editing YAML text in place while preserving comments and formatting.
+
+use crate::de::{Deserializer, DeserializerOptions};
+use crate::error::{Error, Result};
+use crate::event::{
+    CollectionStyle, Emitter, Event, MappingStart, Parser, Scalar, ScalarStyle, SequenceStart,
+};
+use crate::ser::{Serializer, SerializerOptions};
+use crate::{Mapping, Value};
+use serde::de::{Deserialize, Error as _};
+use serde::ser::Serialize;
+use std::fmt::{self, Display};
+use std::iter::Peekable;
+use std::str::FromStr;
+use std::vec;
+
+/// A YAML document that can be edited without disturbing the parts of the
+/// text that are not edited.
+///
+/// `Document` keeps the original text rather than a parsed representation.
+/// Each edit locates the node being changed and rewrites only the bytes it
+/// spans, so comments, blank lines, quoting and indentation everywhere else
+/// come out exactly as they went in. Values written by an edit are formatted
+/// the way [`serde_yaml::to_string`][crate::to_string] formats them, in flow
+/// style inside a flow collection, and indented to fit their surroundings.
+///
+/// Nodes are addressed by a path of mapping keys and sequence indices
+/// separated by `.`, like `dependencies.serde.version` or `jobs.0.name`. The
+/// empty path is the root node. A segment that is a number indexes into a
+/// sequence and is otherwise compared against the string value of mapping
+/// keys, so keys that contain `.` or that are not strings cannot be addressed.
+/// A path may not pass through an alias.
+///
+/// After every edit the text is parsed again, and the edit is rejected with an
+/// error if anything other than the addressed node would have changed.
+///
+/// ```
+/// use serde_yaml::Document;
+///
+/// let yaml = "\
+/// name: app  # display name
+/// dependencies:
+///   serde: {version: '1.0', features: [derive]}  # keep in sync
+///   log: '0.4'
+/// ";
+///
+/// let mut doc: Document = yaml.parse().unwrap();
+/// doc.set("name", "server").unwrap();
+/// doc.set("dependencies.serde.version", "1.0.200").unwrap();
+/// doc.insert("dependencies.anyhow", "1.0").unwrap();
+/// doc.remove("dependencies.log").unwrap();
+///
+/// assert_eq!(doc.to_string(), "\
+/// name: server  # display name
+/// dependencies:
+///   serde: {version: 1.0.200, features: [derive]}  # keep in sync
+///   anyhow: '1.0'
+/// ");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Document {
+    source: String,
+}
+
+impl Document {
+    /// The text of the document, including all edits so far.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the value at `path`, or `None` if there is no such node.
+    pub fn get(&self, path: &str) -> Option<Value> {
+        let root = parse_tree(&self.source).ok()??;
+        let indices = locate(&root, path).ok()?.indices;
+        let mut value: Value = crate::from_str(&self.source).ok()?;
+        value_at(&mut value, &indices).cloned()
+    }
+
+    /// Replaces the value at `path`, which must already exist.
+    pub fn set<T>(&mut self, path: &str, value: T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let value = crate::to_value(value)?;
+        let root = self.root()?;
+        let located = locate(&root, path)?;
+        let edit = replace(&self.source, &root, &located, &value)?;
+        self.apply(path, edit, |root| {
+            *value_at(root, &located.indices).unwrap() = value;
+        })
+    }
+
+    /// Adds a value at `path`.
+    ///
+    /// If the parent of `path` is a mapping, the entry is added after the
+    /// existing entries, or its value is replaced if the key is already
+    /// present. If the parent is a sequence, the last segment of `path` is the
+    /// index at which to insert, from 0 up to and including the length of the
+    /// sequence.
+    pub fn insert<T>(&mut self, path: &str, value: T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let value = crate::to_value(value)?;
+        let root = self.root()?;
+        let (parent, last) = split_last(path).ok_or_else(|| not_found(path))?;
+        let located = locate(&root, parent)?;
+        let (edit, index) = match &located.node.kind {
+            Kind::Mapping { flow, entries } => {
+                if entries.iter().any(|(key, _)| key.is_str(last)) {
+                    return self.set(path, value);
+                }
+                let key = Value::String(last.to_owned());
+                let edit = if *flow {
+                    insert_flow_entry(located.node, entries, &key, &value)?
+                } else {
+                    insert_block_entry(&self.source, &root, entries, &key, &value)?
+                };
+                (edit, None)
+            }
+            Kind::Sequence {
+                flow,
+                dashes,
+                items,
+            } => {
+                let index = match last.parse::<usize>() {
+                    Ok(index) if index <= items.len() => index,
+                    _ => return Err(not_found(path)),
+                };
+                let edit = if *flow {
+                    insert_flow_item(located.node, items, index, &value)?
+                } else {
+                    insert_block_item(&self.source, dashes, items, index, &value)?
+                };
+                (edit, Some(index))
+            }
+            Kind::Scalar(_) | Kind::Alias => return Err(not_found(path)),
+        };
+        self.apply(path, edit, |root| {
+            match value_at(root, &located.indices).unwrap().untag_mut() {
+                Value::Mapping(mapping) => {
+                    mapping.insert(Value::String(last.to_owned()), value);
+                }
+                Value::Sequence(sequence) => sequence.insert(index.unwrap(), value),
+                _ => unreachable!(),
+            }
+        })
+    }
+
+    /// Removes the node at `path` from its parent mapping or sequence, and
+    /// returns its value.
+    ///
+    /// Removing the only entry of a collection leaves behind an empty `{}` or
+    /// `[]`.
+    pub fn remove(&mut self, path: &str) -> Result<Value> {
+        let root = self.root()?;
+        let (parent, _) = split_last(path).ok_or_else(|| not_found(path))?;
+        let located = locate(&root, path)?;
+        let parent = locate(&root, parent)?;
+        let index = *located.indices.last().unwrap();
+        let edit = match &parent.node.kind {
+            Kind::Mapping { entries, .. } if entries.len() == 1 => replace(
+                &self.source,
+                &root,
+                &parent,
+                &Value::Mapping(Mapping::new()),
+            )?,
+            Kind::Sequence { items, .. } if items.len() == 1 => {
+                replace(&self.source, &root, &parent, &Value::Sequence(Vec::new()))?
+            }
+            Kind::Mapping { flow, entries } => {
+                let spans: Vec<_> = entries.iter().map(|(k, v)| (k.start, v.end)).collect();
+                remove_entry(&self.source, *flow, &spans, index)
+            }
+            Kind::Sequence {
+                flow,
+                dashes,
+                items,
+            } => {
+                let spans: Vec<_> = if *flow {
+                    items.iter().map(|item| (item.start, item.end)).collect()
+                } else {
+                    dashes
+                        .iter()
+                        .zip(items)
+                        .map(|(d, item)| (*d, item.end))
+                        .collect()
+                };
+                remove_entry(&self.source, *flow, &spans, index)
+            }
+            Kind::Scalar(_) | Kind::Alias => unreachable!(),
+        };
+        let removed = self.get(path).ok_or_else(|| not_found(path))?;
+        self.apply(path, edit, |root| {
+            match value_at(root, &parent.indices).unwrap().untag_mut() {
+                Value::Mapping(mapping) => {
+                    let key = mapping.keys().nth(index).unwrap().clone();
+                    mapping.shift_remove(&key);
+                }
+                Value::Sequence(sequence) => {
+                    sequence.remove(index);
+                }
+                _ => unreachable!(),
+            }
+        })?;
+        Ok(removed)
+    }
+
+    fn root(&self) -> Result<Tree> {
+        parse_tree(&self.source)?.ok_or_else(|| Error::custom("the document is empty"))
+    }
+
+    // Splices the edit into the text, keeping the result only if it parses to
+    // the original value with `expect` applied.
+    fn apply<F>(&mut self, path: &str, edit: Edit, expect: F) -> Result<()>
+    where
+        F: FnOnce(&mut Value),
+    {
+        let mut expected = load(&self.source)?;
+        expect(&mut expected);
+        // New text is rendered with `\n` line breaks.
+        let text = match line_break(&self.source, edit.start) {
+            "\n" => edit.text,
+            line_break => edit.text.replace('\n', line_break),
+        };
+        let mut source = String::with_capacity(self.source.len() + text.len());
+        source.push_str(&self.source[..edit.start]);
+        source.push_str(&text);
+        source.push_str(&self.source[edit.end..]);
+        match (parse_tree(&source), load(&source)) {
+            (Ok(Some(_)), Ok(actual)) if actual == expected => {
+                self.source = source;
+                Ok(())
+            }
+            _ => Err(Error::custom(format_args!(
+                "editing `{}` would change other parts of the document",
+                path,
+            ))),
+        }
+    }
+}
+
+/// Replaces the value at `path` in YAML text, leaving the rest of the text
+/// unchanged.
+///
+/// This is a shorthand for [`Document::set`] on a document parsed from
+/// `source`, for when only a single edit is needed. Only the bytes spanned by
+/// the node at `path` are rewritten, and the new value is indented to fit in
+/// place of the old one.
+///
+/// ```
+/// # use serde_derive::Serialize;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Dependency {
+///     version: &'static str,
+///     features: Vec<&'static str>,
+/// }
+///
+/// let yaml = "\
+/// dependencies:
+///   serde: '1.0'
+///   log: '0.4'  # pinned
+/// ";
+///
+/// let serde = Dependency {
+///     version: "1.0.200",
+///     features: vec!["derive"],
+/// };
+/// let patched = serde_yaml::document::patch(yaml, "dependencies.serde", serde).unwrap();
+/// assert_eq!(patched, "\
+/// dependencies:
+///   serde:
+///     version: 1.0.200
+///     features:
+///     - derive
+///   log: '0.4'  # pinned
+/// ");
+/// ```
+pub fn patch<T>(source: &str, path: &str, value: T) -> Result<String>
+where
+    T: Serialize,
+{
+    let mut document: Document = source.parse()?;
+    document.set(path, value)?;
+    Ok(document.source)
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_tree(s)?;
+        load(s)?;
+        Ok(Document {
+            source: s.to_owned(),
+        })
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.source)
+    }
+}
+
+// Anchors are kept as tags, so that an edit to an anchored node is seen only
+// where it was made and not at every alias.
+fn load(source: &str) -> Result<Value> {
+    let options = DeserializerOptions::new().preserve_anchors(true);
+    Value::deserialize(Deserializer::from_str(source).with_options(options))
+}
+
+fn value_at<'a>(mut value: &'a mut Value, indices: &[usize]) -> Option<&'a mut Value> {
+    for &index in indices {
+        value = match value.untag_mut() {
+            Value::Sequence(sequence) => sequence.get_mut(index)?,
+            Value::Mapping(mapping) => {
+                let key = mapping.keys().nth(index)?.clone();
+                mapping.get_mut(&key)?
+            }
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+fn split_last(path: &str) -> Option<(&str, &str)> {
+    if path.is_empty() {
+        None
+    } else {
+        Some(match path.rfind('.') {
+            Some(dot) => (&path[..dot], &path[dot + 1..]),
+            None => ("", path),
+        })
+    }
+}
+
+fn not_found(path: &str) -> Error {
+    Error::custom(format_args!("no node at `{}`", path))
+}
+
+// The bytes of the text spanned by a node. A block collection ends where its
+// last descendant ends, and a scalar excludes trailing whitespace and line
+// breaks. An empty scalar, as in `key:` or `-` with nothing after, is placed
+// right after the `:` or `-`.
+struct Tree {
+    start: usize,
+    end: usize,
+    kind: Kind,
+}
+
+enum Kind {
+    Scalar(String),
+    Alias,
+    Sequence {
+        flow: bool,
+        // Position of the `-` introducing each item of a block sequence.
+        dashes: Vec<usize>,
+        items: Vec<Tree>,
+    },
+    Mapping {
+        flow: bool,
+        entries: Vec<(Tree, Tree)>,
+    },
+}
+
+impl Tree {
+    fn is_str(&self, string: &str) -> bool {
+        match &self.kind {
+            Kind::Scalar(scalar) => scalar == string,
+            _ => false,
+        }
+    }
+
+    fn is_empty_scalar(&self) -> bool {
+        match &self.kind {
+            Kind::Scalar(scalar) => scalar.is_empty() && self.start == self.end,
+            _ => false,
+        }
+    }
+}
+
+type Events<'input> = Peekable<vec::IntoIter<(usize, usize, Event<'input>)>>;
+
+fn parse_tree(source: &str) -> Result<Option<Tree>> {
+    let mut events = Vec::new();
+    for event in Parser::from_str(source) {
+        let event = event?;
+        events.push((
+            event.start().index(),
+            event.end().index(),
+            event.into_inner(),
+        ));
+    }
+    let mut events = events.into_iter().peekable();
+    let mut root = None;
+    while let Some((_, _, event)) = events.next() {
+        if let Event::DocumentStart = event {
+            if root.is_some() {
+                return Err(Error::custom(
+                    "a Document cannot hold more than one YAML document",
+                ));
+            }
+            root = Some(build(source, &mut events));
+        }
+    }
+    Ok(root)
+}
+
+fn build(source: &str, events: &mut Events) -> Tree {
+    let (start, end, event) = events.next().unwrap();
+    let flow = match &event {
+        Event::SequenceStart(sequence) => sequence.style == CollectionStyle::Flow,
+        Event::MappingStart(mapping) => mapping.style == CollectionStyle::Flow,
+        _ => false,
+    };
+    let kind = match event {
+        Event::Scalar(scalar) => {
+            return Tree {
+                start,
+                end: start + source[start..end].trim_end().len(),
+                kind: Kind::Scalar(scalar.value.into_owned()),
+            };
+        }
+        Event::Alias(_) => {
+            return Tree {
+                start,
+                end,
+                kind: Kind::Alias,
+            };
+        }
+        Event::SequenceStart(_) => {
+            let mut dashes = Vec::new();
+            let mut items: Vec<Tree> = Vec::new();
+            while !is_end(events) {
+                let mut item = build(source, events);
+                if !flow {
+                    let from = items.last().map_or(start, |item| item.end);
+                    let dash = find_indicator(source, from, b'-').unwrap_or(item.start);
+                    if item.is_empty_scalar() {
+                        item.start = dash + 1;
+                        item.end = dash + 1;
+                    }
+                    dashes.push(dash);
+                }
+                items.push(item);
+            }
+            Kind::Sequence {
+                flow,
+                dashes,
+                items,
+            }
+        }
+        Event::MappingStart(_) => {
+            let mut entries: Vec<(Tree, Tree)> = Vec::new();
+            while !is_end(events) {
+                let key = build(source, events);
+                let mut value = build(source, events);
+                if value.is_empty_scalar() {
+                    let colon = find_indicator(source, key.end, b':').map_or(key.end, |i| i + 1);
+                    value.start = colon;
+                    value.end = colon;
+                }
+                entries.push((key, value));
+            }
+            Kind::Mapping { flow, entries }
+        }
+        Event::StreamStart
+        | Event::StreamEnd
+        | Event::DocumentStart
+        | Event::DocumentEnd
+        | Event::SequenceEnd
+        | Event::MappingEnd => unreachable!(),
+    };
+    let (_, end_of_flow, _) = events.next().unwrap();
+    let end = if flow {
+        end_of_flow
+    } else {
+        match &kind {
+            Kind::Sequence { items, .. } => items.last().map_or(start, |item| item.end),
+            Kind::Mapping { entries, .. } => entries.last().map_or(start, |(_, value)| value.end),
+            Kind::Scalar(_) | Kind::Alias => unreachable!(),
+        }
+    };
+    Tree { start, end, kind }
+}
+
+fn is_end(events: &mut Events) -> bool {
+    matches!(
+        events.peek(),
+        Some((_, _, Event::SequenceEnd | Event::MappingEnd)),
+    )
+}
+
+// Finds `indicator` at or after `from`, skipping over whitespace, comments,
+// anchors and tags.
+fn find_indicator(source: &str, from: usize, indicator: u8) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut i = from;
+    while i < bytes.len() {
+        match bytes[i] {
+            b if b == indicator => return Some(i),
+            b' ' | b'\t' | b'\r' | b'\n' => i += 1,
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'&' | b'!' => {
+                while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+struct Located<'a> {
+    node: &'a Tree,
+    context: Context<'a>,
+    // Position of the node within each enclosing collection, from the root.
+    indices: Vec<usize>,
+}
+
+enum Context<'a> {
+    Root,
+    MappingValue { flow: bool, key: &'a Tree },
+    SequenceItem { flow: bool },
+}
+
+fn locate<'a>(root: &'a Tree, path: &str) -> Result<Located<'a>> {
+    let mut located = Located {
+        node: root,
+        context: Context::Root,
+        indices: Vec::new(),
+    };
+    if path.is_empty() {
+        return Ok(located);
+    }
+    for segment in path.split('.') {
+        let (index, node, context) = match &located.node.kind {
+            Kind::Mapping { flow, entries } => entries
+                .iter()
+                .position(|(key, _)| key.is_str(segment))
+                .map(|i| {
+                    let (key, value) = &entries[i];
+                    (i, value, Context::MappingValue { flow: *flow, key })
+                }),
+            Kind::Sequence { flow, items, .. } => segment
+                .parse::<usize>()
+                .ok()
+                .filter(|&i| i < items.len())
+                .map(|i| (i, &items[i], Context::SequenceItem { flow: *flow })),
+            Kind::Alias => {
+                return Err(Error::custom(format_args!(
+                    "path `{}` goes through an alias",
+                    path,
+                )));
+            }
+            Kind::Scalar(_) => None,
+        }
+        .ok_or_else(|| not_found(path))?;
+        located.node = node;
+        located.context = context;
+        located.indices.push(index);
+    }
+    Ok(located)
+}
+
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+// How far the document indents a block mapping or sequence under a key,
+// relative to the key, going by the first instance of each in the document.
+#[derive(Copy, Clone)]
+struct Indent {
+    mapping: usize,
+    sequence: usize,
+}
+
+impl Indent {
+    fn detect(source: &str, root: &Tree) -> Self {
+        fn visit(
+            source: &str,
+            tree: &Tree,
+            mapping: &mut Option<usize>,
+            sequence: &mut Option<usize>,
+        ) {
+            match &tree.kind {
+                Kind::Scalar(_) | Kind::Alias => {}
+                Kind::Sequence { items, .. } => {
+                    for item in items {
+                        visit(source, item, mapping, sequence);
+                    }
+                }
+                Kind::Mapping { flow, entries } => {
+                    for (key, value) in entries {
+                        let key_column = column(source, key.start);
+                        match &value.kind {
+                            Kind::Mapping {
+                                flow: false,
+                                entries,
+                            } if mapping.is_none() => {
+                                let child_column = column(source, entries[0].0.start);
+                                *mapping = child_column.checked_sub(key_column);
+                            }
+                            Kind::Sequence {
+                                flow: false,
+                                dashes,
+                                ..
+                            } if sequence.is_none() && !*flow => {
+                                let child_column = column(source, dashes[0]);
+                                *sequence = child_column.checked_sub(key_column);
+                            }
+                            _ => {}
+                        }
+                        visit(source, value, mapping, sequence);
+                    }
+                }
+            }
+        }
+
+        let mut mapping = None;
+        let mut sequence = None;
+        visit(source, root, &mut mapping, &mut sequence);
+        Indent {
+            mapping: mapping.filter(|&n| n > 0).unwrap_or(2),
+            sequence: sequence.unwrap_or(0),
+        }
+    }
+}
+
+fn replace(source: &str, root: &Tree, located: &Located, value: &Value) -> Result<Edit> {
+    let node = located.node;
+    let mut edit = Edit {
+        start: node.start,
+        end: node.end,
+        text: String::new(),
+    };
+    match located.context {
+        Context::MappingValue { flow: true, .. } | Context::SequenceItem { flow: true } => {
+            edit.text = render_flow(value)?;
+        }
+        Context::MappingValue { flow: false, key } => {
+            let key_column = column(source, key.start);
+            match find_indicator(source, key.end, b':') {
+                // Unless a comment separates the key from its value, start
+                // right after the colon so that a block collection can move
+                // onto its own line, or a scalar back onto the key's.
+                Some(colon) if !source[colon..node.start].contains('#') => {
+                    let indent = Indent::detect(source, root);
+                    edit.start = colon + 1;
+                    edit.text = render_mapping_value(value, key_column, indent)?;
+                }
+                _ => edit.text = render_block(value, column(source, node.start))?,
+            }
+        }
+        Context::SequenceItem { flow: false } | Context::Root => {
+            edit.text = render_block(value, column(source, node.start))?;
+        }
+    }
+    if node.is_empty_scalar() && !edit.text.starts_with(|ch: char| ch.is_ascii_whitespace()) {
+        edit.text.insert(0, ' ');
+    }
+    Ok(edit)
+}
+
+fn insert_flow_entry(
+    mapping: &Tree,
+    entries: &[(Tree, Tree)],
+    key: &Value,
+    value: &Value,
+) -> Result<Edit> {
+    let entry = format!("{}: {}", render_flow(key)?, render_flow(value)?);
+    Ok(insert_flow(
+        mapping,
+        entries.last().map(|(_, value)| value),
+        entry,
+    ))
+}
+
+fn insert_flow_item(sequence: &Tree, items: &[Tree], index: usize, value: &Value) -> Result<Edit> {
+    let item = render_flow(value)?;
+    Ok(match items.get(index) {
+        Some(next) => Edit {
+            start: next.start,
+            end: next.start,
+            text: item + ", ",
+        },
+        None => insert_flow(sequence, items.last(), item),
+    })
+}
+
+fn insert_flow(collection: &Tree, last: Option<&Tree>, text: String) -> Edit {
+    match last {
+        Some(last) => Edit {
+            start: last.end,
+            end: last.end,
+            text: format!(", {}", text),
+        },
+        // Before the closing bracket.
+        None => Edit {
+            start: collection.end - 1,
+            end: collection.end - 1,
+            text,
+        },
+    }
+}
+
+fn insert_block_entry(
+    source: &str,
+    root: &Tree,
+    entries: &[(Tree, Tree)],
+    key: &Value,
+    value: &Value,
+) -> Result<Edit> {
+    let key_column = column(source, entries[0].0.start);
+    let indent = Indent::detect(source, root);
+    let line = format!(
+        "{}{}:{}",
+        spaces(key_column),
+        render_flow(key)?,
+        render_mapping_value(value, key_column, indent)?,
+    );
+    let (_, last) = entries.last().unwrap();
+    Ok(insert_line(source, last.end, line))
+}
+
+fn insert_block_item(
+    source: &str,
+    dashes: &[usize],
+    items: &[Tree],
+    index: usize,
+    value: &Value,
+) -> Result<Edit> {
+    let dash_column = column(source, dashes[0]);
+    let item = format!("- {}", render_block(value, dash_column + 2)?);
+    Ok(match dashes.get(index) {
+        Some(&dash) if starts_line(source, dash) => Edit {
+            start: dash - dash_column,
+            end: dash - dash_column,
+            text: format!("{}{}\n", spaces(dash_column), item),
+        },
+        // A sequence that starts on the line of an enclosing `- `.
+        Some(&dash) => Edit {
+            start: dash,
+            end: dash,
+            text: format!("{}\n{}", item, spaces(dash_column)),
+        },
+        None => {
+            let line = format!("{}{}", spaces(dash_column), item);
+            insert_line(source, items.last().unwrap().end, line)
+        }
+    })
+}
+
+// Inserts a line after the line containing `pos`.
+fn insert_line(source: &str, pos: usize, line: String) -> Edit {
+    let eol = end_of_line(source, pos);
+    let text = if source[..eol].ends_with('\n') {
+        line + "\n"
+    } else {
+        format!("\n{}", line)
+    };
+    Edit {
+        start: eol,
+        end: eol,
+        text,
+    }
+}
+
+// Removes one of several entries, each given by where it starts and ends.
+fn remove_entry(source: &str, flow: bool, spans: &[(usize, usize)], index: usize) -> Edit {
+    let (start, end) = spans[index];
+    let next = spans.get(index + 1).map(|(start, _)| *start);
+    let edit = if flow {
+        match next {
+            Some(next) => (start, next),
+            None => (spans[index - 1].1, end),
+        }
+    } else if starts_line(source, start) {
+        (start - column(source, start), end_of_line(source, end))
+    } else {
+        // The first entry of a mapping or sequence that starts on the line of
+        // an enclosing `- `.
+        (start, next.unwrap_or(end))
+    };
+    Edit {
+        start: edit.0,
+        end: edit.1,
+        text: String::new(),
+    }
+}
+
+fn column(source: &str, pos: usize) -> usize {
+    pos - source[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+fn starts_line(source: &str, pos: usize) -> bool {
+    source[pos - column(source, pos)..pos]
+        .bytes()
+        .all(|b| b == b' ')
+}
+
+// Position after the line break that ends the line containing `pos`.
+fn end_of_line(source: &str, pos: usize) -> usize {
+    source[pos..]
+        .find('\n')
+        .map_or(source.len(), |i| pos + i + 1)
+}
+
+// The line break ending the line containing `pos`, or the line before it if
+// that line is the last and has none.
+fn line_break(source: &str, pos: usize) -> &'static str {
+    let eol = end_of_line(source, pos);
+    let terminated = if source[..eol].ends_with('\n') {
+        &source[..eol]
+    } else {
+        &source[..pos - column(source, pos)]
+    };
+    if terminated.ends_with("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+fn spaces(n: usize) -> String {
+    " ".repeat(n)
+}
+
+// The text following `key:` for the given value.
+fn render_mapping_value(value: &Value, key_column: usize, indent: Indent) -> Result<String> {
+    let column = match value.untag_ref() {
+        Value::Mapping(mapping) if !mapping.is_empty() => key_column + indent.mapping,
+        Value::Sequence(sequence) if !sequence.is_empty() => key_column + indent.sequence,
+        _ => key_column,
+    };
+    let text = render_block(value, column)?;
+    Ok(match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => format!("\n{}{}", spaces(column), text),
+        Value::Sequence(sequence) if !sequence.is_empty() => {
+            format!("\n{}{}", spaces(column), text)
+        }
+        _ => format!(" {}", text),
+    })
+}
+
+// Serializes the value in block style, for text starting at the given column.
+// Anchors and aliases loaded from the document are written back as such.
+fn render_block(value: &Value, column: usize) -> Result<String> {
+    let options = SerializerOptions::new().preserve_anchors(true);
+    let mut ser = Serializer::new(Vec::new()).with_options(options);
+    value.serialize(&mut ser)?;
+    let yaml = String::from_utf8(ser.into_inner()?).unwrap();
+    let mut lines = yaml.trim_end_matches('\n').split('\n');
+    let mut text = lines.next().unwrap().to_owned();
+    for line in lines {
+        text.push('\n');
+        if !line.is_empty() {
+            text.push_str(&spaces(column));
+            text.push_str(line);
+        }
+    }
+    Ok(text)
+}
+
+// Serializes the value in flow style, on one line.
+fn render_flow(value: &Value) -> Result<String> {
+    let mut emitter = Emitter::new(Vec::new());
+    emitter.emit(Event::StreamStart)?;
+    emitter.emit(Event::DocumentStart)?;
+    emit_flow(&mut emitter, value, None)?;
+    emitter.emit(Event::DocumentEnd)?;
+    emitter.emit(Event::StreamEnd)?;
+    let yaml = String::from_utf8(emitter.into_inner()?).unwrap();
+    Ok(yaml.trim_end_matches('\n').to_owned())
+}
+
+fn emit_flow(emitter: &mut Emitter<Vec<u8>>, value: &Value, tag: Option<String>) -> Result<()> {
+    match value {
+        Value::Sequence(sequence) => {
+            emitter.emit(Event::SequenceStart(SequenceStart {
+                anchor: None,
+                tag,
+                style: CollectionStyle::Flow,
+            }))?;
+            for item in sequence {
+                emit_flow(emitter, item, None)?;
+            }
+            emitter.emit(Event::SequenceEnd)
+        }
+        Value::Mapping(mapping) => {
+            emitter.emit(Event::MappingStart(MappingStart {
+                anchor: None,
+                tag,
+                style: CollectionStyle::Flow,
+            }))?;
+            for (key, value) in mapping {
+                emit_flow(emitter, key, None)?;
+                emit_flow(emitter, value, None)?;
+            }
+            emitter.emit(Event::MappingEnd)
+        }
+        Value::Tagged(tagged) => {
+            let tag = tagged.tag.to_string();
+            let tag = match tag.strip_prefix("!!") {
+                Some(suffix) => format!("tag:yaml.org,2002:{}", suffix),
+                None => tag,
+            };
+            emit_flow(emitter, &tagged.value, Some(tag))
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {
+            // Quote the same way as the serializer, which knows which strings
+            // would otherwise be read back as something else.
+            let yaml = crate::to_string(value)?;
+            let (value, style) = match (value, yaml.as_bytes()[0]) {
+                (Value::String(string), b'\'') => (string.as_str(), ScalarStyle::SingleQuoted),
+                (Value::String(string), b'"' | b'|' | b'>') => {
+                    (string.as_str(), ScalarStyle::DoubleQuoted)
+                }
+                _ => (yaml.trim_end_matches('\n'), ScalarStyle::Plain),
+            };
+            emitter.emit(Event::Scalar(Scalar {
+                anchor: None,
+                tag,
+                value: value.into(),
+                style,
+            }))
+        }
+    }
+}
@@ -1,5 +1,7 @@
-use crate::libyaml::{emitter, error as libyaml};
-use crate::path::Path;
+use crate::libyaml::{emitter, error as libyaml, parser};
+use crate::mapping::DuplicateKeyError;
+use crate::path::{DisplaySegments, Path, PathSegment};
+use crate::value::Value;
 use serde::{de, ser};
 use std::error::Error as StdError;
 use std::fmt::{self, Debug, Display};
@@ -25,28 +27,132 @@ pub(crate) enum ErrorImpl {
     EndOfStream,
     MoreThanOneDocument,
     RecursionLimitExceeded(libyaml::Mark),
-    RepetitionLimitExceeded,
-    BytesUnsupported,
+    RepetitionLimitExceeded(libyaml::Mark),
+    EventLimitExceeded(libyaml::Mark),
+    ScalarLengthLimitExceeded(libyaml::Mark),
+    DocumentSizeLimitExceeded(libyaml::Mark),
     UnknownAnchor(libyaml::Mark),
+    DuplicateKey(Box<DuplicateKey>),
     SerializeNestedEnum,
+    AliasWithValue,
     ScalarInMerge,
     TaggedInMerge,
     ScalarInMergeElement,
     SequenceInMergeElement,
     EmptyTag,
     FailedToParseNumber,
+    FailedToParseTimestamp,
 
     Shared(Arc<ErrorImpl>),
 }
 
+#[derive(Debug)]
+pub(crate) struct DuplicateKey {
+    key: Value,
+    first: libyaml::Mark,
+    pos: Pos,
+}
+
 #[derive(Debug)]
 pub(crate) struct Pos {
     mark: libyaml::Mark,
-    path: String,
+    end: libyaml::Mark,
+    segments: Vec<PathSegment>,
+}
+
+/// The category of an [`Error`], as returned by [`Error::kind`].
+///
+/// More kinds may be added in the future, so matching on an `ErrorKind` needs
+/// a wildcard arm.
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_yaml::{DeserializerOptions, ErrorKind, Value};
+///
+/// fn status(kind: ErrorKind) -> u16 {
+///     match kind {
+///         ErrorKind::Io => 500,
+///         kind if kind.is_limit_exceeded() => 413,
+///         ErrorKind::Syntax | ErrorKind::EndOfStream => 400,
+///         _ => 422,
+///     }
+/// }
+///
+/// let error = serde_yaml::from_str::<Value>("[unclosed").unwrap_err();
+/// assert_eq!(error.kind(), ErrorKind::Syntax);
+/// assert_eq!(status(error.kind()), 400);
+///
+/// let error = serde_yaml::from_str::<u8>("256").unwrap_err();
+/// assert_eq!(error.kind(), ErrorKind::Data);
+/// assert_eq!(status(error.kind()), 422);
+///
+/// let options = DeserializerOptions::new().max_depth(2);
+/// let de = serde_yaml::Deserializer::from_str("[[[0]]]").with_options(options);
+/// let error = Value::deserialize(de).unwrap_err();
+/// assert_eq!(error.kind(), ErrorKind::RecursionLimitExceeded);
+/// assert_eq!(status(error.kind()), 413);
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Reading the input or writing the output failed.
+    Io,
+    /// The input is not valid YAML, or is not valid UTF-8.
+    Syntax,
+    /// The serializer was unable to produce valid YAML, for example because
+    /// of a mapping key that YAML cannot represent.
+    Emit,
+    /// The input ended in the middle of a value, or contained no document
+    /// where one was required.
+    EndOfStream,
+    /// The input contained more than one document where only one was
+    /// expected.
+    MoreThanOneDocument,
+    /// Values were nested deeper than the recursion limit.
+    RecursionLimitExceeded,
+    /// Aliases were expanded more times than allowed.
+    RepetitionLimitExceeded,
+    /// A document contained more parser events than allowed.
+    EventLimitExceeded,
+    /// A scalar was longer than allowed.
+    ScalarLengthLimitExceeded,
+    /// A document was larger than allowed.
+    DocumentSizeLimitExceeded,
+    /// An alias referred to an anchor that has not been defined.
+    UnknownAnchor,
+    /// A mapping contained the same key twice.
+    DuplicateKey,
+    /// A `<<` merge key referred to something other than a mapping or a
+    /// sequence of mappings.
+    InvalidMerge,
+    /// The data has a shape that YAML cannot represent, such as an enum
+    /// nested inside another enum.
+    Unsupported,
+    /// The input is valid YAML but does not match the type being
+    /// deserialized, for example because of a missing field or a string where
+    /// a number was expected. Errors raised by `Serialize` and `Deserialize`
+    /// implementations through `serde::ser::Error::custom` and
+    /// `serde::de::Error::custom` are also of this kind.
+    Data,
+}
+
+impl ErrorKind {
+    /// Whether the error is due to one of the limits configured in
+    /// [`DeserializerOptions`][crate::DeserializerOptions].
+    pub fn is_limit_exceeded(self) -> bool {
+        match self {
+            ErrorKind::RecursionLimitExceeded
+            | ErrorKind::RepetitionLimitExceeded
+            | ErrorKind::EventLimitExceeded
+            | ErrorKind::ScalarLengthLimitExceeded
+            | ErrorKind::DocumentSizeLimitExceeded => true,
+            _ => false,
+        }
+    }
 }
 
 /// The input location that an error occured.
-#[derive(Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct Location {
     index: usize,
     line: usize,
@@ -69,9 +175,17 @@ impl Location {
         self.column
     }
 
+    pub(crate) fn new(index: usize, line: usize, column: usize) -> Self {
+        Location {
+            index,
+            line,
+            column,
+        }
+    }
+
     // This is to keep decoupled with the yaml crate
     #[doc(hidden)]
-    fn from_mark(mark: libyaml::Mark) -> Self {
+    pub(crate) fn from_mark(mark: libyaml::Mark) -> Self {
         Location {
             index: mark.index() as usize,
             // `line` and `column` returned from libyaml are 0-indexed but all error messages add +1 to this value
@@ -102,6 +216,252 @@ impl Error {
     pub fn location(&self) -> Option<Location> {
         self.0.location()
     }
+
+    /// Returns the location immediately past the end of the YAML node that
+    /// the error is about, if there is one.
+    ///
+    /// Together with [`location`][Error::location], this is the span of the
+    /// offending node. It is available for errors deserializing a particular
+    /// value, such as a type mismatch, but not for errors at a single point in
+    /// the input like a syntax error.
+    ///
+    /// ```
+    /// let yaml = "ports: [80, http]\n";
+    /// let error = serde_yaml::from_str::<std::collections::BTreeMap<String, Vec<u16>>>(yaml)
+    ///     .unwrap_err();
+    ///
+    /// let start = error.location().unwrap();
+    /// let end = error.end_location().unwrap();
+    /// assert_eq!(&yaml[start.index()..end.index()], "http");
+    /// ```
+    pub fn end_location(&self) -> Option<Location> {
+        self.0.end_mark().map(Location::from_mark)
+    }
+
+    /// For a syntax error in the middle of a construct that started earlier,
+    /// returns where the construct started.
+    ///
+    /// This is the location that the error message refers to with "while
+    /// parsing a block mapping" or similar.
+    ///
+    /// ```
+    /// let yaml = "jobs:\n  build: [cargo, build\n  test: cargo test\n";
+    /// let error = serde_yaml::from_str::<serde_yaml::Value>(yaml).unwrap_err();
+    ///
+    /// let location = error.location().unwrap();
+    /// assert_eq!((location.line(), location.column()), (3, 7));
+    /// let context = error.context_location().unwrap();
+    /// assert_eq!((context.line(), context.column()), (2, 10));
+    /// ```
+    pub fn context_location(&self) -> Option<Location> {
+        self.0.context_mark().map(Location::from_mark)
+    }
+
+    /// Formats the error for a person to read, in the style of a compiler
+    /// diagnostic: the message, the file name and location, and the lines of
+    /// `source` involved with the offending part underlined.
+    ///
+    /// `source` must be the input that the error came from.
+    ///
+    /// ```
+    /// let yaml = "jobs:\n  build: [cargo, build\n  test: cargo test\n";
+    /// let error = serde_yaml::from_str::<serde_yaml::Value>(yaml).unwrap_err();
+    ///
+    /// let expected = "\
+    /// error: did not find expected ',' or ']'
+    ///  --> ci.yaml:3:7
+    ///   |
+    /// 2 |   build: [cargo, build
+    ///   |          - while parsing a flow sequence
+    /// 3 |   test: cargo test
+    ///   |       ^
+    /// ";
+    /// assert_eq!(error.render(yaml, "ci.yaml").to_string(), expected);
+    /// ```
+    pub fn render<'a>(&'a self, source: &'a str, filename: &'a str) -> Diagnostic<'a> {
+        Diagnostic {
+            error: self,
+            source,
+            filename,
+            color: false,
+        }
+    }
+
+    /// The category of the error.
+    pub fn kind(&self) -> ErrorKind {
+        self.0.kind()
+    }
+
+    /// The path from the root of the document to the value at which the error
+    /// occurred, if the error happened while deserializing a particular
+    /// value. An empty path refers to the root.
+    ///
+    /// ```
+    /// # use serde_derive::Deserialize;
+    /// use serde::Deserialize;
+    /// use serde_yaml::PathSegment;
+    /// use std::collections::BTreeMap;
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// struct Manifest {
+    ///     dependencies: BTreeMap<String, Dependency>,
+    /// }
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// struct Dependency {
+    ///     version: String,
+    ///     features: Vec<String>,
+    /// }
+    ///
+    /// let yaml = "dependencies:\n  serde: {version: '1.0', features: [derive, {}]}\n";
+    /// let error = serde_yaml::from_str::<Manifest>(yaml).unwrap_err();
+    ///
+    /// let path = error.path().unwrap();
+    /// assert_eq!(path, [
+    ///     PathSegment::Key("dependencies".to_owned()),
+    ///     PathSegment::Key("serde".to_owned()),
+    ///     PathSegment::Key("features".to_owned()),
+    ///     PathSegment::Index(1),
+    /// ]);
+    /// ```
+    pub fn path(&self) -> Option<&[PathSegment]> {
+        self.0.path()
+    }
+}
+
+/// An [`Error`] formatted along with an excerpt of the input, as returned by
+/// [`Error::render`].
+#[derive(Copy, Clone, Debug)]
+pub struct Diagnostic<'a> {
+    error: &'a Error,
+    source: &'a str,
+    filename: &'a str,
+    color: bool,
+}
+
+impl<'a> Diagnostic<'a> {
+    /// Whether to highlight the output with ANSI escape codes, for printing
+    /// to a terminal. Off by default.
+    pub fn color(mut self, enable: bool) -> Self {
+        self.color = enable;
+        self
+    }
+}
+
+struct Label {
+    line: usize,
+    // Columns are counted in characters from 0.
+    column: usize,
+    width: usize,
+    primary: bool,
+    text: Option<String>,
+}
+
+impl<'a> Display for Diagnostic<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const RED: &str = "\x1b[1;31m";
+        const BLUE: &str = "\x1b[1;34m";
+        const BOLD: &str = "\x1b[1m";
+        const RESET: &str = "\x1b[0m";
+
+        let paint = |f: &mut fmt::Formatter, style: &str, text: &dyn Display| {
+            if self.color {
+                write!(f, "{}{}{}", style, text, RESET)
+            } else {
+                write!(f, "{}", text)
+            }
+        };
+
+        struct Message<'a>(&'a ErrorImpl);
+        impl<'a> Display for Message<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.0.message(f)
+            }
+        }
+
+        paint(f, RED, &"error")?;
+        paint(f, BOLD, &format_args!(": {}", Message(&self.error.0)))?;
+        f.write_str("\n")?;
+
+        let start = match self.error.location() {
+            Some(start) => start,
+            None => {
+                paint(f, BLUE, &" --> ")?;
+                return writeln!(f, "{}", self.filename);
+            }
+        };
+
+        let lines: Vec<&str> = self.source.lines().collect();
+        let line_text = |line: usize| lines.get(line - 1).copied().unwrap_or("");
+        let line_width = |line: usize| line_text(line).chars().count();
+
+        let mut labels = vec![Label {
+            line: start.line,
+            column: start.column - 1,
+            width: match self.error.end_location() {
+                Some(end) if end.line == start.line => end.column.saturating_sub(start.column),
+                Some(_) => line_width(start.line).saturating_sub(start.column - 1),
+                None => 1,
+            }
+            .max(1),
+            primary: true,
+            text: None,
+        }];
+        if let (Some(context), Some(text)) = (self.error.context_location(), self.error.0.context())
+        {
+            if (context.line, context.column) == (start.line, start.column) {
+                labels[0].text = Some(text);
+            } else {
+                labels.push(Label {
+                    line: context.line,
+                    column: context.column - 1,
+                    width: 1,
+                    primary: false,
+                    text: Some(text),
+                });
+            }
+        }
+        labels.sort_by_key(|label| label.line);
+
+        let gutter = labels.last().unwrap().line.to_string().len();
+        let margin = |f: &mut fmt::Formatter| paint(f, BLUE, &format_args!("{:gutter$} |", ""));
+        paint(f, BLUE, &format_args!("{:gutter$}--> ", ""))?;
+        writeln!(f, "{}:{}:{}", self.filename, start.line, start.column)?;
+        margin(f)?;
+        f.write_str("\n")?;
+
+        let mut previous = None;
+        for label in &labels {
+            if previous != Some(label.line) {
+                if previous.map_or(false, |previous| label.line > previous + 1) {
+                    paint(f, BLUE, &"...")?;
+                    f.write_str("\n")?;
+                }
+                paint(f, BLUE, &format_args!("{:gutter$} |", label.line))?;
+                let text = line_text(label.line);
+                if text.is_empty() {
+                    f.write_str("\n")?;
+                } else {
+                    writeln!(f, " {}", text)?;
+                }
+                previous = Some(label.line);
+            }
+            margin(f)?;
+            let (style, mark) = if label.primary {
+                (RED, "^")
+            } else {
+                (BLUE, "-")
+            };
+            write!(f, " {:1$}", "", label.column)?;
+            let underline = mark.repeat(label.width);
+            match &label.text {
+                Some(text) => paint(f, style, &format_args!("{} {}", underline, text))?,
+                None => paint(f, style, &underline)?,
+            }
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
 }
 
 pub(crate) fn new(inner: ErrorImpl) -> Error {
@@ -112,16 +472,50 @@ pub(crate) fn shared(shared: Arc<ErrorImpl>) -> Error {
     Error(Box::new(ErrorImpl::Shared(shared)))
 }
 
-pub(crate) fn fix_mark(mut error: Error, mark: libyaml::Mark, path: Path) -> Error {
+pub(crate) fn fix_mark(
+    mut error: Error,
+    mark: libyaml::Mark,
+    end: impl FnOnce() -> libyaml::Mark,
+    path: Path,
+) -> Error {
     if let ErrorImpl::Message(_, none @ None) = error.0.as_mut() {
         *none = Some(Pos {
             mark,
-            path: path.to_string(),
+            end: end(),
+            segments: path.segments(),
         });
     }
     error
 }
 
+pub(crate) fn duplicate_key(
+    key: Value,
+    first: libyaml::Mark,
+    mark: libyaml::Mark,
+    end: libyaml::Mark,
+    path: Path,
+) -> Error {
+    new(ErrorImpl::DuplicateKey(Box::new(DuplicateKey {
+        key,
+        first,
+        pos: Pos {
+            mark,
+            end,
+            segments: path.segments(),
+        },
+    })))
+}
+
+impl Pos {
+    fn write_path(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = DisplaySegments(&self.segments).to_string();
+        if path != "." {
+            write!(f, "{}: ", path)?;
+        }
+        Ok(())
+    }
+}
+
 impl Error {
     pub(crate) fn shared(self) -> Arc<ErrorImpl> {
         if let ErrorImpl::Shared(err) = *self.0 {
@@ -138,6 +532,15 @@ impl From<libyaml::Error> for Error {
     }
 }
 
+impl From<parser::Error> for Error {
+    fn from(err: parser::Error) -> Self {
+        match err {
+            parser::Error::Libyaml(err) => Self::from(err),
+            parser::Error::Io(err) => new(ErrorImpl::Io(err)),
+        }
+    }
+}
+
 impl From<emitter::Error> for Error {
     fn from(err: emitter::Error) -> Self {
         match err {
@@ -184,6 +587,44 @@ impl ErrorImpl {
         self.mark().map(Location::from_mark)
     }
 
+    fn path(&self) -> Option<&[PathSegment]> {
+        match self {
+            ErrorImpl::Message(_, Some(pos)) => Some(&pos.segments),
+            ErrorImpl::DuplicateKey(err) => Some(&err.pos.segments),
+            ErrorImpl::Shared(err) => err.path(),
+            _ => None,
+        }
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ErrorImpl::Message(..)
+            | ErrorImpl::AliasWithValue
+            | ErrorImpl::EmptyTag
+            | ErrorImpl::FailedToParseNumber
+            | ErrorImpl::FailedToParseTimestamp => ErrorKind::Data,
+            ErrorImpl::Libyaml(err) if err.is_emitter_error() => ErrorKind::Emit,
+            ErrorImpl::Libyaml(_) => ErrorKind::Syntax,
+            ErrorImpl::Io(_) => ErrorKind::Io,
+            ErrorImpl::FromUtf8(_) => ErrorKind::Emit,
+            ErrorImpl::EndOfStream => ErrorKind::EndOfStream,
+            ErrorImpl::MoreThanOneDocument => ErrorKind::MoreThanOneDocument,
+            ErrorImpl::RecursionLimitExceeded(_) => ErrorKind::RecursionLimitExceeded,
+            ErrorImpl::RepetitionLimitExceeded(_) => ErrorKind::RepetitionLimitExceeded,
+            ErrorImpl::EventLimitExceeded(_) => ErrorKind::EventLimitExceeded,
+            ErrorImpl::ScalarLengthLimitExceeded(_) => ErrorKind::ScalarLengthLimitExceeded,
+            ErrorImpl::DocumentSizeLimitExceeded(_) => ErrorKind::DocumentSizeLimitExceeded,
+            ErrorImpl::UnknownAnchor(_) => ErrorKind::UnknownAnchor,
+            ErrorImpl::DuplicateKey(_) => ErrorKind::DuplicateKey,
+            ErrorImpl::SerializeNestedEnum => ErrorKind::Unsupported,
+            ErrorImpl::ScalarInMerge
+            | ErrorImpl::TaggedInMerge
+            | ErrorImpl::ScalarInMergeElement
+            | ErrorImpl::SequenceInMergeElement => ErrorKind::InvalidMerge,
+            ErrorImpl::Shared(err) => err.kind(),
+        }
+    }
+
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match self {
             ErrorImpl::Io(err) => err.source(),
@@ -193,24 +634,61 @@ impl ErrorImpl {
         }
     }
 
+    fn end_mark(&self) -> Option<libyaml::Mark> {
+        match self {
+            ErrorImpl::Message(_, Some(pos)) => Some(pos.end),
+            ErrorImpl::DuplicateKey(err) => Some(err.pos.end),
+            ErrorImpl::Shared(err) => err.end_mark(),
+            _ => None,
+        }
+    }
+
+    fn context_mark(&self) -> Option<libyaml::Mark> {
+        match self {
+            ErrorImpl::Libyaml(err) => err.context_mark(),
+            ErrorImpl::Shared(err) => err.context_mark(),
+            _ => None,
+        }
+    }
+
     fn mark(&self) -> Option<libyaml::Mark> {
         match self {
-            ErrorImpl::Message(_, Some(Pos { mark, path: _ }))
+            ErrorImpl::Message(_, Some(Pos { mark, .. }))
             | ErrorImpl::RecursionLimitExceeded(mark)
+            | ErrorImpl::RepetitionLimitExceeded(mark)
+            | ErrorImpl::EventLimitExceeded(mark)
+            | ErrorImpl::ScalarLengthLimitExceeded(mark)
+            | ErrorImpl::DocumentSizeLimitExceeded(mark)
             | ErrorImpl::UnknownAnchor(mark) => Some(*mark),
+            ErrorImpl::DuplicateKey(err) => Some(err.pos.mark),
             ErrorImpl::Libyaml(err) => Some(err.mark()),
             ErrorImpl::Shared(err) => err.mark(),
             _ => None,
         }
     }
 
+    // The message without the location, for rendering as a diagnostic.
+    fn message(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorImpl::Libyaml(err) => Display::fmt(&err.problem(), f),
+            ErrorImpl::Shared(err) => err.message(f),
+            _ => self.message_no_mark(f),
+        }
+    }
+
+    fn context(&self) -> Option<String> {
+        match self {
+            ErrorImpl::Libyaml(err) => err.context().map(|context| context.to_string()),
+            ErrorImpl::Shared(err) => err.context(),
+            _ => None,
+        }
+    }
+
     fn message_no_mark(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ErrorImpl::Message(msg, None) => f.write_str(msg),
-            ErrorImpl::Message(msg, Some(Pos { mark: _, path })) => {
-                if path != "." {
-                    write!(f, "{}: ", path)?;
-                }
+            ErrorImpl::Message(msg, Some(pos)) => {
+                pos.write_path(f)?;
                 f.write_str(msg)
             }
             ErrorImpl::Libyaml(_) => unreachable!(),
@@ -221,14 +699,24 @@ impl ErrorImpl {
                 "deserializing from YAML containing more than one document is not supported",
             ),
             ErrorImpl::RecursionLimitExceeded(_mark) => f.write_str("recursion limit exceeded"),
-            ErrorImpl::RepetitionLimitExceeded => f.write_str("repetition limit exceeded"),
-            ErrorImpl::BytesUnsupported => {
-                f.write_str("serialization and deserialization of bytes in YAML is not implemented")
+            ErrorImpl::RepetitionLimitExceeded(_mark) => f.write_str("repetition limit exceeded"),
+            ErrorImpl::EventLimitExceeded(_mark) => f.write_str("event limit exceeded"),
+            ErrorImpl::ScalarLengthLimitExceeded(_mark) => {
+                f.write_str("scalar length limit exceeded")
+            }
+            ErrorImpl::DocumentSizeLimitExceeded(_mark) => {
+                f.write_str("document size limit exceeded")
             }
             ErrorImpl::UnknownAnchor(_mark) => f.write_str("unknown anchor"),
+            ErrorImpl::DuplicateKey(err) => {
+                err.pos.write_path(f)?;
+                let key = DuplicateKeyError { key: &err.key };
+                write!(f, "{} (first defined at {})", key, err.first)
+            }
             ErrorImpl::SerializeNestedEnum => {
                 f.write_str("serializing nested enums in YAML is not supported yet")
             }
+            ErrorImpl::AliasWithValue => f.write_str("a YAML alias cannot have a value"),
             ErrorImpl::ScalarInMerge => {
                 f.write_str("expected a mapping or list of mappings for merging, but found scalar")
             }
@@ -241,6 +729,7 @@ impl ErrorImpl {
             }
             ErrorImpl::EmptyTag => f.write_str("empty YAML tag is not allowed"),
             ErrorImpl::FailedToParseNumber => f.write_str("failed to parse YAML number"),
+            ErrorImpl::FailedToParseTimestamp => f.write_str("failed to parse YAML timestamp"),
             ErrorImpl::Shared(_) => unreachable!(),
         }
     }
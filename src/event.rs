@@ -0,0 +1,432 @@
+//! Streaming access to YAML at the level of parser and emitter events.
+//!
+//! A [`Parser`] reads YAML one event at a time — the start of a document, a
+//! scalar, the start or end of a sequence or mapping — without building a
+//! [`Value`][crate::Value] or going through serde. This is useful for tools
+//! that only need to look at part of a large input, or that care about details
+//! of the YAML syntax, such as anchors and scalar styles, that deserialization
+//! discards.
+//!
+//! An [`Emitter`] goes the other way, writing YAML from a sequence of the same
+//! events. It gives full control over the shape of the output, including
+//! anchors, aliases, tags and the style of every node.
+
+use crate::de;
+use crate::error::{Error, Location, Result};
+use crate::libyaml::emitter;
+use crate::libyaml::parser::{self as libyaml, Anchor};
+use crate::libyaml::tag::Tag;
+use crate::spanned::Spanned;
+use std::borrow::Cow;
+use std::fmt::{self, Debug};
+use std::io;
+use std::marker::PhantomData;
+use std::mem;
+use std::str;
+
+/// An iterator over the events of a YAML stream.
+///
+/// Each event comes with the location in the input where it starts and ends.
+/// The stream begins with [`Event::StreamStart`] and, unless there is an error,
+/// finishes with [`Event::StreamEnd`]. After an error, the iterator produces no
+/// more events.
+///
+/// ```
+/// use serde_yaml::event::{Event, Parser};
+///
+/// let yaml = "name: serde_yaml\nversion: 0.9\n---\nname: serde\nversion: 1.0\n";
+///
+/// let mut documents = 0;
+/// let mut versions = Vec::new();
+/// let mut key = None;
+/// for event in Parser::from_str(yaml) {
+///     match event.unwrap().into_inner() {
+///         Event::DocumentStart => documents += 1,
+///         // In these flat mappings, scalars alternate between key and value.
+///         Event::Scalar(scalar) => match key.take() {
+///             None => key = Some(scalar.value),
+///             Some(key) if key == "version" => versions.push(scalar.value),
+///             Some(_) => {}
+///         },
+///         _ => {}
+///     }
+/// }
+///
+/// assert_eq!(documents, 2);
+/// assert_eq!(versions, ["0.9", "1.0"]);
+/// ```
+pub struct Parser<'input> {
+    parser: Option<libyaml::Parser<'input>>,
+}
+
+/// An event produced by a [`Parser`] or consumed by an [`Emitter`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Event<'a> {
+    /// The beginning of the input.
+    StreamStart,
+    /// The end of the input.
+    StreamEnd,
+    /// The beginning of a document, whether or not it is marked by `---`.
+    DocumentStart,
+    /// The end of a document, whether or not it is marked by `...`.
+    DocumentEnd,
+    /// A reference to an earlier node, like `*name`, holding the name of the
+    /// anchor.
+    Alias(String),
+    /// A scalar.
+    Scalar(Scalar<'a>),
+    /// The beginning of a sequence, followed by events for each element and
+    /// then [`Event::SequenceEnd`].
+    SequenceStart(SequenceStart),
+    /// The end of a sequence.
+    SequenceEnd,
+    /// The beginning of a mapping, followed by events for each key and value
+    /// in turn and then [`Event::MappingEnd`].
+    MappingStart(MappingStart),
+    /// The end of a mapping.
+    MappingEnd,
+}
+
+/// A scalar, with its anchor and tag if any.
+///
+/// Tags are given in full after resolving any tag handle, so `!!str` is
+/// `tag:yaml.org,2002:str` while a local tag like `!thing` is unchanged. A
+/// scalar without a tag is untyped; deciding whether for example `true` is a
+/// boolean or a string is left to the consumer of the events.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Scalar<'a> {
+    /// The name of the anchor, as in `&name`.
+    pub anchor: Option<String>,
+    /// The tag, as in `!tag`.
+    pub tag: Option<String>,
+    /// The contents of the scalar, after processing escapes, line folding and
+    /// indentation. Borrowed from the input where possible.
+    pub value: Cow<'a, str>,
+    /// How the scalar is written. The emitter falls back to another style if
+    /// the value cannot be written in this one, for example a multi-line
+    /// string as a plain scalar in a flow collection.
+    pub style: ScalarStyle,
+}
+
+/// The beginning of a sequence, with its anchor and tag if any.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SequenceStart {
+    /// The name of the anchor, as in `&name`.
+    pub anchor: Option<String>,
+    /// The tag, as in `!tag`.
+    pub tag: Option<String>,
+    /// Whether the sequence is written `[a, b]` or as a list of `- ` items.
+    pub style: CollectionStyle,
+}
+
+/// The beginning of a mapping, with its anchor and tag if any.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MappingStart {
+    /// The name of the anchor, as in `&name`.
+    pub anchor: Option<String>,
+    /// The tag, as in `!tag`.
+    pub tag: Option<String>,
+    /// Whether the mapping is written `{k: v}` or as `k: v` lines.
+    pub style: CollectionStyle,
+}
+
+/// The syntax in which a scalar is written.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ScalarStyle {
+    /// An unquoted scalar, like `value`.
+    Plain,
+    /// A scalar in single quotes, like `'value'`.
+    SingleQuoted,
+    /// A scalar in double quotes, like `"value"`.
+    DoubleQuoted,
+    /// A block scalar introduced by `|`, which keeps line breaks.
+    Literal,
+    /// A block scalar introduced by `>`, which folds line breaks into spaces.
+    Folded,
+}
+
+/// The syntax in which a sequence or mapping is written.
+///
+/// Everything nested inside a flow collection is also written in flow style,
+/// so the emitter writes a block collection inside a flow one as flow.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum CollectionStyle {
+    /// Indentation-based, with each sequence element or mapping entry on its
+    /// own line.
+    Block,
+    /// JSON-like, with `[]` or `{}` brackets.
+    Flow,
+}
+
+impl<'input> Parser<'input> {
+    /// Parses YAML from a string.
+    pub fn from_str(input: &'input str) -> Self {
+        Parser {
+            parser: Some(libyaml::Parser::new(input.as_bytes())),
+        }
+    }
+
+    /// Parses YAML from a byte slice.
+    pub fn from_slice(input: &'input [u8]) -> Self {
+        Parser {
+            parser: Some(libyaml::Parser::new(input)),
+        }
+    }
+
+    /// Parses YAML from an `io::Read`.
+    ///
+    /// Scalars are never borrowed from a reader, so their values are always
+    /// `Cow::Owned`.
+    pub fn from_reader<R>(rdr: R) -> Self
+    where
+        R: io::Read + 'input,
+    {
+        Parser {
+            parser: Some(libyaml::Parser::from_reader(Box::new(rdr))),
+        }
+    }
+}
+
+impl<'input> Iterator for Parser<'input> {
+    type Item = Result<Spanned<Event<'input>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let parser = self.parser.as_mut()?;
+        let (event, start, end) = match parser.next() {
+            Ok(next) => next,
+            Err(err) => {
+                // The parser does not recover from errors.
+                self.parser = None;
+                return Some(Err(Error::from(err)));
+            }
+        };
+        let event = match event {
+            libyaml::Event::StreamStart => Event::StreamStart,
+            libyaml::Event::StreamEnd => {
+                self.parser = None;
+                Event::StreamEnd
+            }
+            libyaml::Event::DocumentStart => Event::DocumentStart,
+            libyaml::Event::DocumentEnd => Event::DocumentEnd,
+            libyaml::Event::Alias(anchor) => Event::Alias(anchor_name(&anchor)),
+            libyaml::Event::Scalar(scalar) => Event::Scalar(Scalar {
+                anchor: scalar.anchor.as_ref().map(anchor_name),
+                tag: scalar.tag.as_ref().map(tag_name),
+                value: scalar_value(&scalar),
+                style: match scalar.style {
+                    libyaml::ScalarStyle::Plain => ScalarStyle::Plain,
+                    libyaml::ScalarStyle::SingleQuoted => ScalarStyle::SingleQuoted,
+                    libyaml::ScalarStyle::DoubleQuoted => ScalarStyle::DoubleQuoted,
+                    libyaml::ScalarStyle::Literal => ScalarStyle::Literal,
+                    libyaml::ScalarStyle::Folded => ScalarStyle::Folded,
+                },
+            }),
+            libyaml::Event::SequenceStart(sequence) => Event::SequenceStart(SequenceStart {
+                anchor: sequence.anchor.as_ref().map(anchor_name),
+                tag: sequence.tag.as_ref().map(tag_name),
+                style: collection_style(sequence.flow),
+            }),
+            libyaml::Event::SequenceEnd => Event::SequenceEnd,
+            libyaml::Event::MappingStart(mapping) => Event::MappingStart(MappingStart {
+                anchor: mapping.anchor.as_ref().map(anchor_name),
+                tag: mapping.tag.as_ref().map(tag_name),
+                style: collection_style(mapping.flow),
+            }),
+            libyaml::Event::MappingEnd => Event::MappingEnd,
+        };
+        Some(Ok(Spanned::new(
+            Location::from_mark(start),
+            Location::from_mark(end),
+            event,
+        )))
+    }
+}
+
+impl<'input> Debug for Parser<'input> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Parser")
+            .field("finished", &self.parser.is_none())
+            .finish()
+    }
+}
+
+// The parser is configured for UTF-8, and libyaml rejects input that is not
+// valid UTF-8, so anchors, tags and scalars are always valid strings.
+
+fn anchor_name(anchor: &Anchor) -> String {
+    String::from_utf8_lossy(anchor).into_owned()
+}
+
+fn tag_name(tag: &Tag) -> String {
+    String::from_utf8_lossy(tag).into_owned()
+}
+
+fn collection_style(flow: bool) -> CollectionStyle {
+    if flow {
+        CollectionStyle::Flow
+    } else {
+        CollectionStyle::Block
+    }
+}
+
+fn scalar_value<'input>(scalar: &libyaml::Scalar<'input>) -> Cow<'input, str> {
+    match str::from_utf8(&scalar.value) {
+        Ok(value) => match de::parse_borrowed_str(value, scalar.repr, scalar.style) {
+            Some(borrowed) => Cow::Borrowed(borrowed),
+            None => Cow::Owned(value.to_owned()),
+        },
+        Err(_) => Cow::Owned(String::from_utf8_lossy(&scalar.value).into_owned()),
+    }
+}
+
+/// A writer of YAML from a sequence of events.
+///
+/// Events must form a valid YAML stream: `StreamStart`, then any number of
+/// documents each consisting of `DocumentStart`, a single node and
+/// `DocumentEnd`, and finally `StreamEnd`. Emitting an event out of place is
+/// an error. Output is buffered until the end of the stream or a call to
+/// [`flush`](Emitter::flush).
+///
+/// Untagged scalars are written as given, even if they would not be read back
+/// as a string. For example a plain scalar `true` is a boolean to most
+/// readers. It is up to the caller to choose a quoted style where that
+/// matters.
+///
+/// ```
+/// use serde_yaml::event::{
+///     CollectionStyle, Emitter, Event, MappingStart, Scalar, ScalarStyle, SequenceStart,
+/// };
+///
+/// let scalar = |value: &'static str| {
+///     Event::Scalar(Scalar {
+///         anchor: None,
+///         tag: None,
+///         value: value.into(),
+///         style: ScalarStyle::Plain,
+///     })
+/// };
+///
+/// let mut emitter = Emitter::new(Vec::new());
+/// let events = vec![
+///     Event::StreamStart,
+///     Event::DocumentStart,
+///     Event::MappingStart(MappingStart {
+///         anchor: None,
+///         tag: None,
+///         style: CollectionStyle::Block,
+///     }),
+///     scalar("defaults"),
+///     Event::MappingStart(MappingStart {
+///         anchor: Some("defaults".to_owned()),
+///         tag: None,
+///         style: CollectionStyle::Flow,
+///     }),
+///     scalar("retries"),
+///     scalar("3"),
+///     Event::MappingEnd,
+///     scalar("jobs"),
+///     Event::SequenceStart(SequenceStart {
+///         anchor: None,
+///         tag: Some("!jobs".to_owned()),
+///         style: CollectionStyle::Block,
+///     }),
+///     Event::Alias("defaults".to_owned()),
+///     Event::SequenceEnd,
+///     Event::MappingEnd,
+///     Event::DocumentEnd,
+///     Event::StreamEnd,
+/// ];
+/// for event in events {
+///     emitter.emit(event).unwrap();
+/// }
+///
+/// let yaml = String::from_utf8(emitter.into_inner().unwrap()).unwrap();
+/// assert_eq!(yaml, "defaults: &defaults {retries: 3}\njobs: !jobs\n- *defaults\n");
+/// ```
+pub struct Emitter<W> {
+    emitter: emitter::Emitter<'static>,
+    writer: PhantomData<W>,
+}
+
+impl<W> Emitter<W>
+where
+    W: io::Write,
+{
+    /// Creates an emitter that writes YAML to `writer`.
+    pub fn new(writer: W) -> Self {
+        let emitter = emitter::Emitter::new({
+            let writer = Box::new(writer);
+            unsafe { mem::transmute::<Box<dyn io::Write>, Box<dyn io::Write>>(writer) }
+        });
+        Emitter {
+            emitter,
+            writer: PhantomData,
+        }
+    }
+
+    /// Writes one event.
+    pub fn emit(&mut self, event: Event) -> Result<()> {
+        let event = match &event {
+            Event::StreamStart => emitter::Event::StreamStart,
+            Event::StreamEnd => emitter::Event::StreamEnd,
+            Event::DocumentStart => emitter::Event::DocumentStart(Default::default()),
+            Event::DocumentEnd => emitter::Event::DocumentEnd(Default::default()),
+            Event::Alias(anchor) => emitter::Event::Alias(anchor.clone()),
+            Event::Scalar(scalar) => emitter::Event::Scalar(emitter::Scalar {
+                anchor: scalar.anchor.clone(),
+                tag: scalar.tag.clone(),
+                value: &scalar.value,
+                style: match scalar.style {
+                    ScalarStyle::Plain => emitter::ScalarStyle::Plain,
+                    ScalarStyle::SingleQuoted => emitter::ScalarStyle::SingleQuoted,
+                    ScalarStyle::DoubleQuoted => emitter::ScalarStyle::DoubleQuoted,
+                    ScalarStyle::Literal => emitter::ScalarStyle::Literal,
+                    ScalarStyle::Folded => emitter::ScalarStyle::Folded,
+                },
+            }),
+            Event::SequenceStart(sequence) => emitter::Event::SequenceStart(emitter::Sequence {
+                anchor: sequence.anchor.clone(),
+                tag: sequence.tag.clone(),
+                style: emitter_style(sequence.style),
+            }),
+            Event::SequenceEnd => emitter::Event::SequenceEnd,
+            Event::MappingStart(mapping) => emitter::Event::MappingStart(emitter::Mapping {
+                anchor: mapping.anchor.clone(),
+                tag: mapping.tag.clone(),
+                style: emitter_style(mapping.style),
+            }),
+            Event::MappingEnd => emitter::Event::MappingEnd,
+        };
+        self.emitter.emit(event)?;
+        Ok(())
+    }
+
+    /// Writes out any buffered output and calls
+    /// [`.flush()`](io::Write::flush) on the underlying `io::Write` object.
+    pub fn flush(&mut self) -> Result<()> {
+        self.emitter.flush()?;
+        Ok(())
+    }
+
+    /// Writes out any buffered output and unwraps the underlying `io::Write`
+    /// object.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.emitter.flush()?;
+        let writer = self.emitter.into_inner();
+        Ok(*unsafe { Box::from_raw(Box::into_raw(writer).cast::<W>()) })
+    }
+}
+
+impl<W> Debug for Emitter<W> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_struct("Emitter").finish_non_exhaustive()
+    }
+}
+
+fn emitter_style(style: CollectionStyle) -> emitter::CollectionStyle {
+    match style {
+        CollectionStyle::Block => emitter::CollectionStyle::Block,
+        CollectionStyle::Flow => emitter::CollectionStyle::Flow,
+    }
+}
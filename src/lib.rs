@@ -160,23 +160,39 @@
     clippy::must_use_candidate,
 )]
 
-pub use crate::de::{from_reader, from_slice, from_str, Deserializer};
-pub use crate::error::{Error, Location, Result};
-pub use crate::ser::{to_string, to_writer, Serializer};
+pub use crate::de::{
+    from_reader, from_slice, from_str, Deserializer, DeserializerOptions, DuplicateKeyPolicy,
+    Schema,
+};
+pub use crate::document::Document;
+pub use crate::error::{Diagnostic, Error, ErrorKind, Location, Result};
+pub use crate::path::PathSegment;
+pub use crate::ser::{to_string, to_writer, QuotePolicy, Serializer, SerializerOptions};
+pub use crate::spanned::Spanned;
+pub use crate::timestamp::Timestamp;
 #[doc(inline)]
 pub use crate::value::{from_value, to_value, Index, Number, Sequence, Value};
 
 #[doc(inline)]
 pub use crate::mapping::Mapping;
 
+#[doc(inline)]
+pub use crate::node::Node;
+
+mod base64;
 mod de;
+pub mod document;
 mod error;
+pub mod event;
 mod libyaml;
 mod loader;
 pub mod mapping;
+pub mod node;
 mod number;
 mod path;
 mod ser;
+mod spanned;
+mod timestamp;
 pub mod value;
 pub mod with;
 
@@ -27,8 +27,9 @@ struct EmitterPinned<'a> {
 pub(crate) enum Event<'a> {
     StreamStart,
     StreamEnd,
-    DocumentStart,
-    DocumentEnd,
+    DocumentStart(DocumentStart),
+    DocumentEnd(DocumentEnd),
+    Alias(String),
     Scalar(Scalar<'a>),
     SequenceStart(Sequence),
     SequenceEnd,
@@ -36,8 +37,21 @@ pub(crate) enum Event<'a> {
     MappingEnd,
 }
 
+#[derive(Debug, Default)]
+pub(crate) struct DocumentStart {
+    pub version: Option<(u8, u8)>,
+    pub tags: Vec<(String, String)>,
+    pub explicit: bool,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct DocumentEnd {
+    pub explicit: bool,
+}
+
 #[derive(Debug)]
 pub(crate) struct Scalar<'a> {
+    pub anchor: Option<String>,
     pub tag: Option<String>,
     pub value: &'a str,
     pub style: ScalarStyle,
@@ -48,17 +62,30 @@ pub(crate) enum ScalarStyle {
     Any,
     Plain,
     SingleQuoted,
+    DoubleQuoted,
     Literal,
+    Folded,
 }
 
 #[derive(Debug)]
 pub(crate) struct Sequence {
+    pub anchor: Option<String>,
     pub tag: Option<String>,
+    pub style: CollectionStyle,
 }
 
 #[derive(Debug)]
 pub(crate) struct Mapping {
+    pub anchor: Option<String>,
     pub tag: Option<String>,
+    pub style: CollectionStyle,
+}
+
+#[derive(Debug)]
+pub(crate) enum CollectionStyle {
+    Any,
+    Block,
+    Flow,
 }
 
 impl<'a> Emitter<'a> {
@@ -89,11 +116,31 @@ impl<'a> Emitter<'a> {
                     sys::yaml_stream_start_event_initialize(sys_event, sys::YAML_UTF8_ENCODING)
                 }
                 Event::StreamEnd => sys::yaml_stream_end_event_initialize(sys_event),
-                Event::DocumentStart => {
-                    let version_directive = ptr::null_mut();
-                    let tag_directives_start = ptr::null_mut();
-                    let tag_directives_end = ptr::null_mut();
-                    let implicit = true;
+                Event::DocumentStart(mut document) => {
+                    let mut version_directive = document.version.map(|(major, minor)| {
+                        let mut version =
+                            MaybeUninit::<sys::yaml_version_directive_t>::zeroed().assume_init();
+                        version.major = major.into();
+                        version.minor = minor.into();
+                        version
+                    });
+                    let version_directive = version_directive
+                        .as_mut()
+                        .map_or_else(ptr::null_mut, |version| version as *mut _);
+                    let mut tag_directives: Vec<_> = document
+                        .tags
+                        .iter_mut()
+                        .map(|(handle, prefix)| {
+                            let mut tag =
+                                MaybeUninit::<sys::yaml_tag_directive_t>::zeroed().assume_init();
+                            tag.handle = nul_terminated(handle) as *mut u8;
+                            tag.prefix = nul_terminated(prefix) as *mut u8;
+                            tag
+                        })
+                        .collect();
+                    let tag_directives_start = tag_directives.as_mut_ptr();
+                    let tag_directives_end = tag_directives_start.add(tag_directives.len());
+                    let implicit = !document.explicit;
                     sys::yaml_document_start_event_initialize(
                         sys_event,
                         version_directive,
@@ -102,16 +149,20 @@ impl<'a> Emitter<'a> {
                         implicit,
                     )
                 }
-                Event::DocumentEnd => {
-                    let implicit = true;
+                Event::DocumentEnd(document) => {
+                    let implicit = !document.explicit;
                     sys::yaml_document_end_event_initialize(sys_event, implicit)
                 }
+                Event::Alias(mut anchor) => {
+                    let anchor = nul_terminated(&mut anchor);
+                    sys::yaml_alias_event_initialize(sys_event, anchor)
+                }
                 Event::Scalar(mut scalar) => {
-                    let anchor = ptr::null();
-                    let tag = scalar.tag.as_mut().map_or_else(ptr::null, |tag| {
-                        tag.push('\0');
-                        tag.as_ptr()
-                    });
+                    let anchor = scalar
+                        .anchor
+                        .as_mut()
+                        .map_or_else(ptr::null, nul_terminated);
+                    let tag = scalar.tag.as_mut().map_or_else(ptr::null, nul_terminated);
                     let value = scalar.value.as_ptr();
                     let length = scalar.value.len() as i32;
                     let plain_implicit = tag.is_null();
@@ -120,7 +171,9 @@ impl<'a> Emitter<'a> {
                         ScalarStyle::Any => sys::YAML_ANY_SCALAR_STYLE,
                         ScalarStyle::Plain => sys::YAML_PLAIN_SCALAR_STYLE,
                         ScalarStyle::SingleQuoted => sys::YAML_SINGLE_QUOTED_SCALAR_STYLE,
+                        ScalarStyle::DoubleQuoted => sys::YAML_DOUBLE_QUOTED_SCALAR_STYLE,
                         ScalarStyle::Literal => sys::YAML_LITERAL_SCALAR_STYLE,
+                        ScalarStyle::Folded => sys::YAML_FOLDED_SCALAR_STYLE,
                     };
                     sys::yaml_scalar_event_initialize(
                         sys_event,
@@ -134,26 +187,34 @@ impl<'a> Emitter<'a> {
                     )
                 }
                 Event::SequenceStart(mut sequence) => {
-                    let anchor = ptr::null();
-                    let tag = sequence.tag.as_mut().map_or_else(ptr::null, |tag| {
-                        tag.push('\0');
-                        tag.as_ptr()
-                    });
+                    let anchor = sequence
+                        .anchor
+                        .as_mut()
+                        .map_or_else(ptr::null, nul_terminated);
+                    let tag = sequence.tag.as_mut().map_or_else(ptr::null, nul_terminated);
                     let implicit = tag.is_null();
-                    let style = sys::YAML_ANY_SEQUENCE_STYLE;
+                    let style = match sequence.style {
+                        CollectionStyle::Any => sys::YAML_ANY_SEQUENCE_STYLE,
+                        CollectionStyle::Block => sys::YAML_BLOCK_SEQUENCE_STYLE,
+                        CollectionStyle::Flow => sys::YAML_FLOW_SEQUENCE_STYLE,
+                    };
                     sys::yaml_sequence_start_event_initialize(
                         sys_event, anchor, tag, implicit, style,
                     )
                 }
                 Event::SequenceEnd => sys::yaml_sequence_end_event_initialize(sys_event),
                 Event::MappingStart(mut mapping) => {
-                    let anchor = ptr::null();
-                    let tag = mapping.tag.as_mut().map_or_else(ptr::null, |tag| {
-                        tag.push('\0');
-                        tag.as_ptr()
-                    });
+                    let anchor = mapping
+                        .anchor
+                        .as_mut()
+                        .map_or_else(ptr::null, nul_terminated);
+                    let tag = mapping.tag.as_mut().map_or_else(ptr::null, nul_terminated);
                     let implicit = tag.is_null();
-                    let style = sys::YAML_ANY_MAPPING_STYLE;
+                    let style = match mapping.style {
+                        CollectionStyle::Any => sys::YAML_ANY_MAPPING_STYLE,
+                        CollectionStyle::Block => sys::YAML_BLOCK_MAPPING_STYLE,
+                        CollectionStyle::Flow => sys::YAML_FLOW_MAPPING_STYLE,
+                    };
                     sys::yaml_mapping_start_event_initialize(
                         sys_event, anchor, tag, implicit, style,
                     )
@@ -180,6 +241,25 @@ impl<'a> Emitter<'a> {
         Ok(())
     }
 
+    pub fn set_indent(&mut self, indent: usize) {
+        unsafe {
+            let emitter = addr_of_mut!((*self.pin.ptr).sys);
+            sys::yaml_emitter_set_indent(emitter, indent as i32);
+        }
+    }
+
+    pub fn set_width(&mut self, width: usize) {
+        let width = i32::try_from(width).unwrap_or(i32::MAX);
+        unsafe {
+            let emitter = addr_of_mut!((*self.pin.ptr).sys);
+            sys::yaml_emitter_set_width(emitter, width);
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut (dyn io::Write + 'a) {
+        unsafe { &mut *(*self.pin.ptr).write }
+    }
+
     pub fn into_inner(self) -> Box<dyn io::Write + 'a> {
         let sink = Box::new(io::sink());
         unsafe { mem::replace(&mut (*self.pin.ptr).write, sink) }
@@ -195,6 +275,11 @@ impl<'a> Emitter<'a> {
     }
 }
 
+fn nul_terminated(string: &mut String) -> *const u8 {
+    string.push('\0');
+    string.as_ptr()
+}
+
 unsafe fn write_handler(data: *mut c_void, buffer: *mut u8, size: u64) -> i32 {
     let data = data.cast::<EmitterPinned>();
     match io::Write::write_all(unsafe { &mut *(*data).write }, unsafe {
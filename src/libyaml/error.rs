@@ -4,8 +4,6 @@ use std::mem::MaybeUninit;
 use std::ptr::NonNull;
 use unsafe_libyaml as sys;
 
-pub(crate) type Result<T> = std::result::Result<T, Error>;
-
 pub(crate) struct Error {
     kind: sys::yaml_error_type_t,
     problem: CStr<'static>,
@@ -60,6 +58,31 @@ impl Error {
     pub fn mark(&self) -> Mark {
         self.problem_mark
     }
+
+    pub fn problem(&self) -> CStr<'static> {
+        self.problem
+    }
+
+    pub fn context(&self) -> Option<CStr<'static>> {
+        self.context
+    }
+
+    pub fn context_mark(&self) -> Option<Mark> {
+        if self.context.is_some()
+            && (self.context_mark.sys.line != 0 || self.context_mark.sys.column != 0)
+        {
+            Some(self.context_mark)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_emitter_error(&self) -> bool {
+        match self.kind {
+            sys::YAML_WRITER_ERROR | sys::YAML_EMITTER_ERROR => true,
+            _ => false,
+        }
+    }
 }
 
 impl Display for Error {
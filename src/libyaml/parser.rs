@@ -1,21 +1,36 @@
+use crate::libyaml;
 use crate::libyaml::cstr::{self, CStr};
-use crate::libyaml::error::{Error, Mark, Result};
+use crate::libyaml::error::Mark;
 use crate::libyaml::tag::Tag;
 use crate::libyaml::util::Owned;
-use std::borrow::Cow;
+use std::ffi::c_void;
 use std::fmt::{self, Debug};
+use std::io;
 use std::mem::MaybeUninit;
+use std::ops::Deref;
 use std::ptr::{addr_of_mut, NonNull};
 use std::slice;
 use unsafe_libyaml as sys;
 
+#[derive(Debug)]
+pub(crate) enum Error {
+    Libyaml(libyaml::error::Error),
+    Io(io::Error),
+}
+
 pub(crate) struct Parser<'input> {
     pin: Owned<ParserPinned<'input>>,
 }
 
 struct ParserPinned<'input> {
     sys: sys::yaml_parser_t,
-    input: Cow<'input, [u8]>,
+    input: Input<'input>,
+    read_error: Option<io::Error>,
+}
+
+enum Input<'input> {
+    Slice(&'input [u8]),
+    Read(Box<dyn io::Read + 'input>),
 }
 
 #[derive(Debug)]
@@ -44,12 +59,14 @@ pub(crate) struct Scalar<'input> {
 pub(crate) struct SequenceStart {
     pub anchor: Option<Anchor>,
     pub tag: Option<Tag>,
+    pub flow: bool,
 }
 
 #[derive(Debug)]
 pub(crate) struct MappingStart {
     pub anchor: Option<Anchor>,
     pub tag: Option<Tag>,
+    pub flow: bool,
 }
 
 #[derive(Ord, PartialOrd, Eq, PartialEq)]
@@ -65,46 +82,98 @@ pub(crate) enum ScalarStyle {
 }
 
 impl<'input> Parser<'input> {
-    pub fn new(input: Cow<'input, [u8]>) -> Parser<'input> {
+    pub fn new(input: &'input [u8]) -> Parser<'input> {
         let owned = Owned::<ParserPinned>::new_uninit();
         let pin = unsafe {
             let parser = addr_of_mut!((*owned.ptr).sys);
             if sys::yaml_parser_initialize(parser).fail {
-                panic!("malloc error: {}", Error::parse_error(parser));
+                panic!("malloc error: {}", libyaml::Error::parse_error(parser));
             }
             sys::yaml_parser_set_encoding(parser, sys::YAML_UTF8_ENCODING);
             sys::yaml_parser_set_input_string(parser, input.as_ptr(), input.len() as u64);
-            addr_of_mut!((*owned.ptr).input).write(input);
+            addr_of_mut!((*owned.ptr).input).write(Input::Slice(input));
+            addr_of_mut!((*owned.ptr).read_error).write(None);
+            Owned::assume_init(owned)
+        };
+        Parser { pin }
+    }
+
+    pub fn from_reader(read: Box<dyn io::Read + 'input>) -> Parser<'input> {
+        let owned = Owned::<ParserPinned>::new_uninit();
+        let pin = unsafe {
+            let parser = addr_of_mut!((*owned.ptr).sys);
+            if sys::yaml_parser_initialize(parser).fail {
+                panic!("malloc error: {}", libyaml::Error::parse_error(parser));
+            }
+            sys::yaml_parser_set_encoding(parser, sys::YAML_UTF8_ENCODING);
+            addr_of_mut!((*owned.ptr).input).write(Input::Read(read));
+            addr_of_mut!((*owned.ptr).read_error).write(None);
+            sys::yaml_parser_set_input(parser, read_handler, owned.ptr.cast());
             Owned::assume_init(owned)
         };
         Parser { pin }
     }
 
-    pub fn next(&mut self) -> Result<(Event<'input>, Mark)> {
+    pub fn next(&mut self) -> Result<(Event<'input>, Mark, Mark), Error> {
         let mut event = MaybeUninit::<sys::yaml_event_t>::uninit();
         unsafe {
             let parser = addr_of_mut!((*self.pin.ptr).sys);
             if (*parser).error != sys::YAML_NO_ERROR {
-                return Err(Error::parse_error(parser));
+                return Err(self.error());
             }
             let event = event.as_mut_ptr();
             if sys::yaml_parser_parse(parser, event).fail {
-                return Err(Error::parse_error(parser));
+                return Err(self.error());
             }
             let ret = convert_event(&*event, &(*self.pin.ptr).input);
-            let mark = Mark {
+            let start = Mark {
                 sys: (*event).start_mark,
             };
+            let end = Mark {
+                sys: (*event).end_mark,
+            };
             sys::yaml_event_delete(event);
-            Ok((ret, mark))
+            Ok((ret, start, end))
+        }
+    }
+
+    fn error(&mut self) -> Error {
+        let parser = unsafe { &mut *self.pin.ptr };
+        if let Some(read_error) = parser.read_error.take() {
+            Error::Io(read_error)
+        } else {
+            Error::Libyaml(unsafe { libyaml::Error::parse_error(&parser.sys) })
         }
     }
 }
 
-unsafe fn convert_event<'input>(
-    sys: &sys::yaml_event_t,
-    input: &Cow<'input, [u8]>,
-) -> Event<'input> {
+unsafe fn read_handler(data: *mut c_void, buffer: *mut u8, size: u64, size_read: *mut u64) -> i32 {
+    let data = data.cast::<ParserPinned>();
+    let read = match unsafe { &mut (*data).input } {
+        Input::Read(read) => read,
+        Input::Slice(_) => unreachable!(),
+    };
+    let buffer = unsafe { slice::from_raw_parts_mut(buffer, size as usize) };
+    loop {
+        match read.read(buffer) {
+            Ok(n) => {
+                unsafe {
+                    *size_read = n as u64;
+                }
+                return 1;
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => {
+                unsafe {
+                    (*data).read_error = Some(err);
+                }
+                return 0;
+            }
+        }
+    }
+}
+
+unsafe fn convert_event<'input>(sys: &sys::yaml_event_t, input: &Input<'input>) -> Event<'input> {
     match sys.type_ {
         sys::YAML_STREAM_START_EVENT => Event::StreamStart,
         sys::YAML_STREAM_END_EVENT => Event::StreamEnd,
@@ -127,7 +196,7 @@ unsafe fn convert_event<'input>(
                 sys::YAML_FOLDED_SCALAR_STYLE => ScalarStyle::Folded,
                 sys::YAML_ANY_SCALAR_STYLE | _ => unreachable!(),
             },
-            repr: if let Cow::Borrowed(input) = input {
+            repr: if let Input::Slice(input) = input {
                 Some(&input[sys.start_mark.index as usize..sys.end_mark.index as usize])
             } else {
                 None
@@ -136,11 +205,13 @@ unsafe fn convert_event<'input>(
         sys::YAML_SEQUENCE_START_EVENT => Event::SequenceStart(SequenceStart {
             anchor: unsafe { optional_anchor(sys.data.sequence_start.anchor) },
             tag: unsafe { optional_tag(sys.data.sequence_start.tag) },
+            flow: unsafe { sys.data.sequence_start.style } == sys::YAML_FLOW_SEQUENCE_STYLE,
         }),
         sys::YAML_SEQUENCE_END_EVENT => Event::SequenceEnd,
         sys::YAML_MAPPING_START_EVENT => Event::MappingStart(MappingStart {
             anchor: unsafe { optional_anchor(sys.data.mapping_start.anchor) },
             tag: unsafe { optional_tag(sys.data.mapping_start.tag) },
+            flow: unsafe { sys.data.mapping_start.style } == sys::YAML_FLOW_MAPPING_STYLE,
         }),
         sys::YAML_MAPPING_END_EVENT => Event::MappingEnd,
         sys::YAML_NO_EVENT => unreachable!(),
@@ -188,6 +259,14 @@ impl<'input> Debug for Scalar<'input> {
     }
 }
 
+impl Deref for Anchor {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 impl Debug for Anchor {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         cstr::debug_lossy(&self.0, formatter)
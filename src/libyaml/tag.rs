@@ -10,6 +10,9 @@ impl Tag {
     pub const BOOL: &'static str = "tag:yaml.org,2002:bool";
     pub const INT: &'static str = "tag:yaml.org,2002:int";
     pub const FLOAT: &'static str = "tag:yaml.org,2002:float";
+    pub const MERGE: &'static str = "tag:yaml.org,2002:merge";
+    pub const BINARY: &'static str = "tag:yaml.org,2002:binary";
+    pub const TIMESTAMP: &'static str = "tag:yaml.org,2002:timestamp";
 }
 
 impl Tag {
@@ -1,8 +1,7 @@
-use crate::de::{Event, Progress};
+use crate::de::{DeserializerOptions, Event, Progress};
 use crate::error::{self, Error, ErrorImpl, Result};
 use crate::libyaml::error::Mark;
-use crate::libyaml::parser::{Event as YamlEvent, Parser};
-use std::borrow::Cow;
+use crate::libyaml::parser::{Anchor, Event as YamlEvent, Parser};
 use std::collections::BTreeMap;
 use std::sync::Arc;
 
@@ -12,35 +11,33 @@ pub(crate) struct Loader<'input> {
 }
 
 pub(crate) struct Document<'input> {
-    pub events: Vec<(Event<'input>, Mark)>,
+    /// Each event with its start and end mark.
+    pub events: Vec<(Event<'input>, Mark, Mark)>,
     pub error: Option<Arc<ErrorImpl>>,
     /// Map from alias id to index in events.
     pub aliases: BTreeMap<usize, usize>,
+    /// If anchors are preserved, map from index in events to `&name` for
+    /// anchored nodes and `*name` for aliases.
+    pub anchor_names: BTreeMap<usize, String>,
 }
 
 impl<'input> Loader<'input> {
     pub fn new(progress: Progress<'input>) -> Result<Self> {
-        let input = match progress {
-            Progress::Str(s) => Cow::Borrowed(s.as_bytes()),
-            Progress::Slice(bytes) => Cow::Borrowed(bytes),
-            Progress::Read(mut rdr) => {
-                let mut buffer = Vec::new();
-                if let Err(io_error) = rdr.read_to_end(&mut buffer) {
-                    return Err(error::new(ErrorImpl::Io(io_error)));
-                }
-                Cow::Owned(buffer)
-            }
+        let parser = match progress {
+            Progress::Str(s) => Parser::new(s.as_bytes()),
+            Progress::Slice(bytes) => Parser::new(bytes),
+            Progress::Read(rdr) => Parser::from_reader(rdr),
             Progress::Iterable(_) | Progress::Document(_) => unreachable!(),
             Progress::Fail(err) => return Err(error::shared(err)),
         };
 
         Ok(Loader {
-            parser: Some(Parser::new(input)),
+            parser: Some(parser),
             document_count: 0,
         })
     }
 
-    pub fn next_document(&mut self) -> Option<Document<'input>> {
+    pub fn next_document(&mut self, options: &DeserializerOptions) -> Option<Document<'input>> {
         let parser = match &mut self.parser {
             Some(parser) => parser,
             None => return None,
@@ -50,27 +47,39 @@ impl<'input> Loader<'input> {
         self.document_count += 1;
 
         let mut anchors = BTreeMap::new();
+        let mut document_start = None;
         let mut document = Document {
             events: Vec::new(),
             error: None,
             aliases: BTreeMap::new(),
+            anchor_names: BTreeMap::new(),
         };
 
         loop {
-            let (event, mark) = match parser.next() {
-                Ok((event, mark)) => (event, mark),
+            let (event, mark, end) = match parser.next() {
+                Ok(next) => next,
                 Err(err) => {
+                    // The parser does not recover from errors, and for a
+                    // reader there is no knowing what input was consumed.
+                    self.parser = None;
                     document.error = Some(Error::from(err).shared());
                     return Some(document);
                 }
             };
+            if let Some(limit) = options.document_bytes {
+                let start = *document_start.get_or_insert(mark.index());
+                if end.index() - start > limit as u64 {
+                    let err = ErrorImpl::DocumentSizeLimitExceeded(mark);
+                    return Some(self.limit_exceeded(document, err));
+                }
+            }
             let event = match event {
                 YamlEvent::StreamStart => continue,
                 YamlEvent::StreamEnd => {
                     self.parser = None;
                     return if first {
                         if document.events.is_empty() {
-                            document.events.push((Event::Void, mark));
+                            document.events.push((Event::Void, mark, mark));
                         }
                         Some(document)
                     } else {
@@ -80,40 +89,88 @@ impl<'input> Loader<'input> {
                 YamlEvent::DocumentStart => continue,
                 YamlEvent::DocumentEnd => return Some(document),
                 YamlEvent::Alias(alias) => match anchors.get(&alias) {
-                    Some(id) => Event::Alias(*id),
+                    Some(id) => {
+                        record_anchor_name(&mut document, '*', &alias, options);
+                        Event::Alias(*id)
+                    }
                     None => {
+                        self.parser = None;
                         document.error = Some(error::new(ErrorImpl::UnknownAnchor(mark)).shared());
                         return Some(document);
                     }
                 },
                 YamlEvent::Scalar(mut scalar) => {
+                    if let Some(limit) = options.scalar_length {
+                        if scalar.value.len() > limit {
+                            let err = ErrorImpl::ScalarLengthLimitExceeded(mark);
+                            return Some(self.limit_exceeded(document, err));
+                        }
+                    }
                     if let Some(anchor) = scalar.anchor.take() {
-                        let id = anchors.len();
-                        anchors.insert(anchor, id);
-                        document.aliases.insert(id, document.events.len());
+                        record_anchor(&mut document, &mut anchors, anchor, options);
                     }
                     Event::Scalar(scalar)
                 }
                 YamlEvent::SequenceStart(mut sequence_start) => {
                     if let Some(anchor) = sequence_start.anchor.take() {
-                        let id = anchors.len();
-                        anchors.insert(anchor, id);
-                        document.aliases.insert(id, document.events.len());
+                        record_anchor(&mut document, &mut anchors, anchor, options);
                     }
                     Event::SequenceStart(sequence_start)
                 }
                 YamlEvent::SequenceEnd => Event::SequenceEnd,
                 YamlEvent::MappingStart(mut mapping_start) => {
                     if let Some(anchor) = mapping_start.anchor.take() {
-                        let id = anchors.len();
-                        anchors.insert(anchor, id);
-                        document.aliases.insert(id, document.events.len());
+                        record_anchor(&mut document, &mut anchors, anchor, options);
                     }
                     Event::MappingStart(mapping_start)
                 }
                 YamlEvent::MappingEnd => Event::MappingEnd,
             };
-            document.events.push((event, mark));
+            if let Some(limit) = options.events {
+                if document.events.len() >= limit {
+                    let err = ErrorImpl::EventLimitExceeded(mark);
+                    return Some(self.limit_exceeded(document, err));
+                }
+            }
+            document.events.push((event, mark, end));
         }
     }
+
+    fn limit_exceeded(
+        &mut self,
+        mut document: Document<'input>,
+        err: ErrorImpl,
+    ) -> Document<'input> {
+        // Stop reading from the input. Whatever follows the offending event is
+        // not going to be loaded anyway.
+        self.parser = None;
+        document.error = Some(error::new(err).shared());
+        document
+    }
+}
+
+// Gives the next event an alias id under which later aliases find it.
+fn record_anchor(
+    document: &mut Document,
+    anchors: &mut BTreeMap<Anchor, usize>,
+    anchor: Anchor,
+    options: &DeserializerOptions,
+) {
+    record_anchor_name(document, '&', &anchor, options);
+    let id = anchors.len();
+    anchors.insert(anchor, id);
+    document.aliases.insert(id, document.events.len());
+}
+
+// Remembers `&name` or `*name` for the next event if anchors are preserved.
+fn record_anchor_name(
+    document: &mut Document,
+    sigil: char,
+    anchor: &Anchor,
+    options: &DeserializerOptions,
+) {
+    if options.preserve_anchors {
+        let name = format!("{}{}", sigil, String::from_utf8_lossy(anchor));
+        document.anchor_names.insert(document.events.len(), name);
+    }
 }
@@ -814,7 +814,9 @@ impl<'de> Deserialize<'de> for Mapping {
                 while let Some(key) = data.next_key()? {
                     match mapping.entry(key) {
                         Entry::Occupied(entry) => {
-                            return Err(serde::de::Error::custom(DuplicateKeyError { entry }));
+                            return Err(serde::de::Error::custom(DuplicateKeyError {
+                                key: entry.key(),
+                            }));
                         }
                         Entry::Vacant(entry) => {
                             let value = data.next_value()?;
@@ -831,14 +833,14 @@ impl<'de> Deserialize<'de> for Mapping {
     }
 }
 
-struct DuplicateKeyError<'a> {
-    entry: OccupiedEntry<'a>,
+pub(crate) struct DuplicateKeyError<'a> {
+    pub key: &'a Value,
 }
 
 impl<'a> Display for DuplicateKeyError<'a> {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         formatter.write_str("duplicate entry ")?;
-        match self.entry.key() {
+        match self.key {
             Value::Null => formatter.write_str("with null key"),
             Value::Bool(boolean) => write!(formatter, "with key `{}`", boolean),
             Value::Number(number) => write!(formatter, "with key {}", number),
@@ -0,0 +1,319 @@
+//! A YAML document tree in which every node knows its location in the input.
+
+use crate::error::Location;
+use crate::value::tagged::TagStringVisitor;
+use crate::value::{Tag, TaggedValue};
+use crate::{Mapping, Number, Spanned, Value};
+use serde::de::{
+    self, Deserialize, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use std::fmt;
+
+/// A YAML node along with the location in the input that it spans.
+///
+/// `Node` holds the same information as [`Value`], plus the start and end
+/// location of every scalar, sequence and mapping, including mapping keys.
+/// This makes it suitable for tools like linters and schema checkers that
+/// operate on arbitrary YAML and need to report positions.
+///
+/// A `Node` can only be deserialized from YAML text, through
+/// [`serde_yaml::Deserializer`][crate::Deserializer] or one of the `from_*`
+/// functions, because locations come from the parser. It converts into a
+/// plain `Value` with [`Node::into_value`], which moves the contents without
+/// copying strings.
+///
+/// ```
+/// use serde_yaml::node::NodeValue;
+/// use serde_yaml::Node;
+///
+/// let yaml = "name: web\nreplicas: -1\n";
+/// let node: Node = serde_yaml::from_str(yaml).unwrap();
+///
+/// if let NodeValue::Mapping(entries) = node.value() {
+///     for (key, value) in entries {
+///         if let NodeValue::Number(n) = value.value() {
+///             if n.as_i64().map_or(false, |n| n < 0) {
+///                 let start = value.start();
+///                 assert_eq!((start.line(), start.column()), (2, 11));
+///                 assert_eq!(key.value(), &NodeValue::String("replicas".to_owned()));
+///             }
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Node {
+    start: Location,
+    end: Location,
+    value: NodeValue,
+}
+
+/// The contents of a [`Node`].
+///
+/// This mirrors [`Value`], except that the elements of sequences and the keys
+/// and values of mappings are `Node`s. Mapping entries are kept in input
+/// order, including any entries with a duplicate key.
+#[derive(Clone, PartialEq, Debug)]
+pub enum NodeValue {
+    /// Represents a YAML null value.
+    Null,
+    /// Represents a YAML boolean.
+    Bool(bool),
+    /// Represents a YAML numerical value, whether integer or floating point.
+    Number(Number),
+    /// Represents a YAML string.
+    String(String),
+    /// Represents a YAML sequence.
+    Sequence(Vec<Node>),
+    /// Represents a YAML mapping.
+    Mapping(Vec<(Node, Node)>),
+    /// A representation of YAML's `!Tag` syntax, used for enums. The tag
+    /// covers the same span of input as the contents.
+    Tagged(Tag, Box<NodeValue>),
+}
+
+impl Node {
+    /// Location of the first character of the node.
+    pub fn start(&self) -> Location {
+        self.start
+    }
+
+    /// Location immediately past the last character of the node.
+    pub fn end(&self) -> Location {
+        self.end
+    }
+
+    /// Borrows the contents of the node.
+    pub fn value(&self) -> &NodeValue {
+        &self.value
+    }
+
+    /// Mutably borrows the contents of the node.
+    pub fn value_mut(&mut self) -> &mut NodeValue {
+        &mut self.value
+    }
+
+    /// Discards all locations and converts the tree into a `Value`.
+    ///
+    /// If a mapping contains more than one entry with the same key, the last
+    /// one wins.
+    pub fn into_value(self) -> Value {
+        self.value.into_value()
+    }
+}
+
+impl NodeValue {
+    /// Discards all locations and converts the tree into a `Value`.
+    ///
+    /// If a mapping contains more than one entry with the same key, the last
+    /// one wins.
+    pub fn into_value(self) -> Value {
+        match self {
+            NodeValue::Null => Value::Null,
+            NodeValue::Bool(b) => Value::Bool(b),
+            NodeValue::Number(n) => Value::Number(n),
+            NodeValue::String(s) => Value::String(s),
+            NodeValue::Sequence(sequence) => {
+                Value::Sequence(sequence.into_iter().map(Node::into_value).collect())
+            }
+            NodeValue::Mapping(entries) => {
+                let mut mapping = Mapping::with_capacity(entries.len());
+                for (k, v) in entries {
+                    mapping.insert(k.into_value(), v.into_value());
+                }
+                Value::Mapping(mapping)
+            }
+            NodeValue::Tagged(tag, value) => Value::Tagged(Box::new(TaggedValue {
+                tag,
+                value: value.into_value(),
+            })),
+        }
+    }
+}
+
+impl From<Node> for Value {
+    fn from(node: Node) -> Self {
+        node.into_value()
+    }
+}
+
+/// Compares the contents of the nodes, disregarding their locations.
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Serialize for Node {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.value.serialize(serializer)
+    }
+}
+
+impl Serialize for NodeValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            NodeValue::Null => serializer.serialize_unit(),
+            NodeValue::Bool(b) => serializer.serialize_bool(*b),
+            NodeValue::Number(n) => n.serialize(serializer),
+            NodeValue::String(s) => serializer.serialize_str(s),
+            NodeValue::Sequence(sequence) => {
+                let mut seq = serializer.serialize_seq(Some(sequence.len()))?;
+                for element in sequence {
+                    seq.serialize_element(element)?;
+                }
+                seq.end()
+            }
+            NodeValue::Mapping(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            NodeValue::Tagged(tag, value) => {
+                struct SerializeTag<'a>(&'a Tag);
+
+                impl<'a> Serialize for SerializeTag<'a> {
+                    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                    where
+                        S: Serializer,
+                    {
+                        serializer.collect_str(self.0)
+                    }
+                }
+
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(&SerializeTag(tag), value)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let spanned = Spanned::<NodeValue>::deserialize(deserializer)?;
+        Ok(Node {
+            start: spanned.start(),
+            end: spanned.end(),
+            value: spanned.into_inner(),
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for NodeValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct NodeValueVisitor;
+
+        impl<'de> Visitor<'de> for NodeValueVisitor {
+            type Value = NodeValue;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("any YAML value")
+            }
+
+            fn visit_bool<E>(self, b: bool) -> Result<NodeValue, E>
+            where
+                E: de::Error,
+            {
+                Ok(NodeValue::Bool(b))
+            }
+
+            fn visit_i64<E>(self, i: i64) -> Result<NodeValue, E>
+            where
+                E: de::Error,
+            {
+                Ok(NodeValue::Number(i.into()))
+            }
+
+            fn visit_u64<E>(self, u: u64) -> Result<NodeValue, E>
+            where
+                E: de::Error,
+            {
+                Ok(NodeValue::Number(u.into()))
+            }
+
+            fn visit_f64<E>(self, f: f64) -> Result<NodeValue, E>
+            where
+                E: de::Error,
+            {
+                Ok(NodeValue::Number(f.into()))
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<NodeValue, E>
+            where
+                E: de::Error,
+            {
+                Ok(NodeValue::String(s.to_owned()))
+            }
+
+            fn visit_string<E>(self, s: String) -> Result<NodeValue, E>
+            where
+                E: de::Error,
+            {
+                Ok(NodeValue::String(s))
+            }
+
+            fn visit_unit<E>(self) -> Result<NodeValue, E>
+            where
+                E: de::Error,
+            {
+                Ok(NodeValue::Null)
+            }
+
+            fn visit_none<E>(self) -> Result<NodeValue, E>
+            where
+                E: de::Error,
+            {
+                Ok(NodeValue::Null)
+            }
+
+            fn visit_seq<A>(self, mut data: A) -> Result<NodeValue, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut sequence = Vec::with_capacity(data.size_hint().unwrap_or(0));
+                while let Some(element) = data.next_element()? {
+                    sequence.push(element);
+                }
+                Ok(NodeValue::Sequence(sequence))
+            }
+
+            fn visit_map<A>(self, mut data: A) -> Result<NodeValue, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut entries = Vec::with_capacity(data.size_hint().unwrap_or(0));
+                while let Some(entry) = data.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(NodeValue::Mapping(entries))
+            }
+
+            fn visit_enum<A>(self, data: A) -> Result<NodeValue, A::Error>
+            where
+                A: EnumAccess<'de>,
+            {
+                let (tag, contents) = data.variant_seed(TagStringVisitor)?;
+                let value = contents.newtype_variant()?;
+                Ok(NodeValue::Tagged(tag, Box::new(value)))
+            }
+        }
+
+        deserializer.deserialize_any(NodeValueVisitor)
+    }
+}
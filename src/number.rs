@@ -314,11 +314,11 @@ impl FromStr for Number {
     type Err = Error;
 
     fn from_str(repr: &str) -> Result<Self, Self::Err> {
-        if let Ok(result) = de::visit_int(NumberVisitor, repr) {
+        if let Ok(result) = de::visit_int(NumberVisitor, repr, de::Schema::Core) {
             return result;
         }
         if !de::digits_but_not_number(repr) {
-            if let Some(float) = de::parse_f64(repr) {
+            if let Some(float) = de::parse_f64(repr, de::Schema::Core) {
                 return Ok(float.into());
             }
         }
@@ -1,5 +1,20 @@
 use std::fmt::{self, Display};
 
+/// One step along the path from the root of a document to the value at which
+/// an error occurred, as returned by [`Error::path`][crate::Error::path].
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum PathSegment {
+    /// The value of a mapping entry whose key is a scalar, holding the
+    /// contents of the key even if it is a number or boolean.
+    Key(String),
+    /// An element of a sequence, by index starting from 0.
+    Index(usize),
+    /// The value that an alias like `*name` refers to.
+    Alias,
+    /// The value of a mapping entry whose key is a sequence or mapping.
+    Unknown,
+}
+
 /// Path to the current value in the input, like `dependencies.serde.typo1`.
 #[derive(Copy, Clone)]
 pub enum Path<'a> {
@@ -32,3 +47,48 @@ impl<'a> Display for Path<'a> {
         }
     }
 }
+
+impl<'a> Path<'a> {
+    pub(crate) fn segments(&self) -> Vec<PathSegment> {
+        let (parent, segment) = match self {
+            Path::Root => return Vec::new(),
+            Path::Seq { parent, index } => (parent, PathSegment::Index(*index)),
+            Path::Map { parent, key } => (parent, PathSegment::Key((*key).to_owned())),
+            Path::Alias { parent } => (parent, PathSegment::Alias),
+            Path::Unknown { parent } => (parent, PathSegment::Unknown),
+        };
+        let mut segments = parent.segments();
+        segments.push(segment);
+        segments
+    }
+}
+
+/// Formats the segments of a path the same way as the [`Path`] they came from.
+pub(crate) struct DisplaySegments<'a>(pub &'a [PathSegment]);
+
+impl<'a> Display for DisplaySegments<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        struct Parent<'a>(&'a [PathSegment]);
+
+        impl<'a> Display for Parent<'a> {
+            fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+                match self.0 {
+                    [] => Ok(()),
+                    path => write!(formatter, "{}.", DisplaySegments(path)),
+                }
+            }
+        }
+
+        let (segment, parent) = match self.0.split_last() {
+            Some(split) => split,
+            None => return formatter.write_str("."),
+        };
+        let display = DisplaySegments(parent);
+        match segment {
+            PathSegment::Index(index) => write!(formatter, "{}[{}]", display, index),
+            PathSegment::Key(key) => write!(formatter, "{}{}", Parent(parent), key),
+            PathSegment::Alias => write!(formatter, "{}", display),
+            PathSegment::Unknown => write!(formatter, "{}?", Parent(parent)),
+        }
+    }
+}
@@ -2,14 +2,23 @@
 //!
 //! This module provides YAML serialization with the type `Serializer`.
 
-use crate::error::{self, Error, ErrorImpl};
+use crate::base64;
+use crate::de::Schema;
+use crate::error::{self, Error, ErrorImpl, Location};
+use crate::event;
 use crate::libyaml;
-use crate::libyaml::emitter::{Emitter, Event, Mapping, Scalar, ScalarStyle, Sequence};
+use crate::libyaml::emitter::{
+    CollectionStyle, DocumentEnd, DocumentStart, Emitter, Event, Mapping, Scalar, ScalarStyle,
+    Sequence,
+};
+use crate::libyaml::tag::Tag;
 use crate::value::tagged::{self, MaybeTag};
 use serde::de::Visitor;
-use serde::ser::{self, Serializer as _};
+use serde::ser;
+use std::cmp;
 use std::fmt::{self, Display};
 use std::io;
+use std::iter;
 use std::marker::PhantomData;
 use std::mem;
 use std::num;
@@ -44,10 +53,109 @@ type Result<T, E = Error> = std::result::Result<T, E>;
 pub struct Serializer<W> {
     depth: usize,
     state: State,
+    anchor: Option<String>,
+    alias: Option<String>,
+    key: bool,
+    flow: bool,
+    pending: Vec<Pending>,
+    candidates: Vec<Candidate>,
+    options: SerializerOptions,
     emitter: Emitter<'static>,
     writer: PhantomData<W>,
 }
 
+/// Configuration of a [`Serializer`]: how the YAML it writes is laid out.
+///
+/// ```
+/// # use serde_derive::Serialize;
+/// use serde::Serialize;
+/// use serde_yaml::{Serializer, SerializerOptions};
+///
+/// #[derive(Serialize)]
+/// struct Service {
+///     name: &'static str,
+///     ports: Vec<u16>,
+/// }
+///
+/// let options = SerializerOptions::new().indent(4).indent_sequences(true);
+/// let mut ser = Serializer::new(Vec::new()).with_options(options);
+///
+/// let services = vec![Service {
+///     name: "web",
+///     ports: vec![80, 443],
+/// }];
+/// services.serialize(&mut ser).unwrap();
+///
+/// let yaml = String::from_utf8(ser.into_inner().unwrap()).unwrap();
+/// assert_eq!(yaml, "-   name: web\n    ports:\n        - 80\n        - 443\n");
+/// ```
+#[derive(Clone, Debug)]
+pub struct SerializerOptions {
+    pub(crate) indent: usize,
+    pub(crate) indent_sequences: bool,
+    pub(crate) mappings_on_dash_line: bool,
+    pub(crate) width: Option<usize>,
+    pub(crate) quote_keys: QuotePolicy,
+    pub(crate) quote_values: QuotePolicy,
+    pub(crate) flow_max_width: Option<usize>,
+    pub(crate) flow_leaves: bool,
+    pub(crate) explicit_start: bool,
+    pub(crate) explicit_end: bool,
+    pub(crate) version_directive: Option<(u8, u8)>,
+    pub(crate) tag_directives: Vec<(String, String)>,
+    pub(crate) preserve_anchors: bool,
+}
+
+/// When a [`Serializer`] puts strings in quotes.
+///
+/// Whatever the policy, a string that cannot be written any other way, such as
+/// one containing control characters, is double-quoted.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_yaml::{QuotePolicy, Serializer, SerializerOptions};
+/// use std::collections::BTreeMap;
+///
+/// let mut map = BTreeMap::new();
+/// map.insert("name", "serde_yaml");
+/// map.insert("enabled", "yes");
+///
+/// let options = SerializerOptions::new().quote_values(QuotePolicy::WhenAmbiguous);
+/// let mut ser = Serializer::new(Vec::new()).with_options(options);
+/// map.serialize(&mut ser).unwrap();
+///
+/// let yaml = String::from_utf8(ser.into_inner().unwrap()).unwrap();
+/// assert_eq!(yaml, "enabled: 'yes'\nname: serde_yaml\n");
+///
+/// let options = SerializerOptions::new().quote_values(QuotePolicy::AlwaysDouble);
+/// let mut ser = Serializer::new(Vec::new()).with_options(options);
+/// map.serialize(&mut ser).unwrap();
+///
+/// let yaml = String::from_utf8(ser.into_inner().unwrap()).unwrap();
+/// assert_eq!(yaml, "enabled: \"yes\"\nname: \"serde_yaml\"\n");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QuotePolicy {
+    /// Single-quote only strings that a reader would take to be something
+    /// other than a string under the YAML 1.2 core schema, such as `'true'`
+    /// and `'1.0'`, leaving strings such as `yes` and `1_000` unquoted. This
+    /// is the default.
+    ///
+    /// A `char` is always single-quoted under this policy.
+    WhenRequired,
+    /// Single-quote strings that a reader would take to be something other
+    /// than a string under either the YAML 1.2 core schema or the YAML 1.1
+    /// rules, such as `'true'`, `'1.0'` and `'yes'`, for output that is read
+    /// by YAML 1.1 tools.
+    ///
+    /// A `char` is always single-quoted under this policy.
+    WhenAmbiguous,
+    /// Single-quote every string.
+    AlwaysSingle,
+    /// Double-quote every string.
+    AlwaysDouble,
+}
+
 enum State {
     NothingInParticular,
     CheckForTag,
@@ -56,6 +164,298 @@ enum State {
     AlreadyTagged,
 }
 
+impl SerializerOptions {
+    /// Creates the default set of options.
+    pub fn new() -> Self {
+        SerializerOptions {
+            indent: 2,
+            indent_sequences: false,
+            mappings_on_dash_line: true,
+            width: None,
+            quote_keys: QuotePolicy::WhenRequired,
+            quote_values: QuotePolicy::WhenRequired,
+            flow_max_width: None,
+            flow_leaves: false,
+            explicit_start: false,
+            explicit_end: false,
+            version_directive: None,
+            tag_directives: Vec::new(),
+            preserve_anchors: false,
+        }
+    }
+
+    /// Number of spaces by which each level of nesting is indented. Defaults
+    /// to 2, and must be between 2 and 9; values outside that range are
+    /// clamped to it.
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent.clamp(2, 9);
+        self
+    }
+
+    /// Indent a block sequence that is the value of a mapping entry relative
+    /// to its key. Disabled by default, in which case the dashes line up with
+    /// the key.
+    ///
+    /// ```
+    /// use serde_yaml::{Serializer, SerializerOptions};
+    /// use serde::Serialize;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut map = BTreeMap::new();
+    /// map.insert("ports", [80, 443]);
+    ///
+    /// let options = SerializerOptions::new().indent_sequences(true);
+    /// let mut ser = Serializer::new(Vec::new()).with_options(options);
+    /// map.serialize(&mut ser).unwrap();
+    ///
+    /// let yaml = String::from_utf8(ser.into_inner().unwrap()).unwrap();
+    /// assert_eq!(yaml, "ports:\n  - 80\n  - 443\n");
+    /// ```
+    pub fn indent_sequences(mut self, enable: bool) -> Self {
+        self.indent_sequences = enable;
+        self
+    }
+
+    /// Start a mapping that is an item of a block sequence on the same line
+    /// as the item's dash, as in `- name: web`. Enabled by default. When
+    /// disabled, the dash is on a line of its own and the mapping starts on
+    /// the next line.
+    pub fn mappings_on_dash_line(mut self, enable: bool) -> Self {
+        self.mappings_on_dash_line = enable;
+        self
+    }
+
+    /// Preferred maximum width of a line, in characters. Unlimited by default.
+    ///
+    /// A string without line breaks that is longer than this is written as a
+    /// folded block scalar (`>`), with its text broken across lines at
+    /// spaces. Plain and quoted scalars are also broken at spaces where they
+    /// run past the limit. Line breaks are only ever added in places where a
+    /// reader folds them back into the original space, so the strings read
+    /// back unchanged, and a word longer than the limit is not split. Widths
+    /// that do not leave room for two levels of indentation are raised to
+    /// the smallest width that does.
+    ///
+    /// ```
+    /// use serde_yaml::{Serializer, SerializerOptions, Value};
+    /// use serde::Serialize;
+    ///
+    /// let mut map = serde_yaml::Mapping::new();
+    /// map.insert(
+    ///     Value::from("description"),
+    ///     Value::from("Deserialize and serialize YAML in Rust, using libyaml"),
+    /// );
+    ///
+    /// let options = SerializerOptions::new().width(30);
+    /// let mut ser = Serializer::new(Vec::new()).with_options(options);
+    /// map.serialize(&mut ser).unwrap();
+    ///
+    /// let yaml = String::from_utf8(ser.into_inner().unwrap()).unwrap();
+    /// assert_eq!(
+    ///     yaml,
+    ///     "description: >-\n  Deserialize and serialize YAML\n  in Rust, using libyaml\n",
+    /// );
+    /// assert_eq!(serde_yaml::from_str::<Value>(&yaml).unwrap(), Value::Mapping(map));
+    /// ```
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// When to quote strings that are mapping keys, including the names of
+    /// struct fields. Defaults to [`QuotePolicy::WhenRequired`].
+    pub fn quote_keys(mut self, policy: QuotePolicy) -> Self {
+        self.quote_keys = policy;
+        self
+    }
+
+    /// When to quote all other strings. Defaults to
+    /// [`QuotePolicy::WhenRequired`].
+    pub fn quote_values(mut self, policy: QuotePolicy) -> Self {
+        self.quote_values = policy;
+        self
+    }
+
+    /// Write a sequence or mapping in flow style, like `[80, 443]` or
+    /// `{x: 1, y: 2}`, if it takes up at most this many characters that way.
+    /// Not set by default, in which case collections are written in block
+    /// style.
+    ///
+    /// The collection at the root of a document is always written in block
+    /// style. If [`flow_leaves`][Self::flow_leaves] is also enabled, only
+    /// collections that satisfy both conditions are written in flow style.
+    /// Individual fields can be written in flow style regardless of these
+    /// options with [`serde_yaml::with::flow`][crate::with::flow].
+    ///
+    /// ```
+    /// # use serde_derive::Serialize;
+    /// use serde::Serialize;
+    /// use serde_yaml::{Serializer, SerializerOptions};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Service {
+    ///     ports: Vec<u16>,
+    ///     hosts: Vec<&'static str>,
+    /// }
+    ///
+    /// let service = Service {
+    ///     ports: vec![80, 443],
+    ///     hosts: vec!["www.example.com", "example.com", "example.org"],
+    /// };
+    ///
+    /// let options = SerializerOptions::new().flow_max_width(20);
+    /// let mut ser = Serializer::new(Vec::new()).with_options(options);
+    /// service.serialize(&mut ser).unwrap();
+    ///
+    /// let yaml = String::from_utf8(ser.into_inner().unwrap()).unwrap();
+    /// assert_eq!(
+    ///     yaml,
+    ///     "ports: [80, 443]\nhosts:\n- www.example.com\n- example.com\n- example.org\n",
+    /// );
+    /// ```
+    pub fn flow_max_width(mut self, width: usize) -> Self {
+        self.flow_max_width = Some(width);
+        self
+    }
+
+    /// Write a sequence or mapping in flow style if its elements are all
+    /// scalars. Disabled by default.
+    ///
+    /// The collection at the root of a document is always written in block
+    /// style. If [`flow_max_width`][Self::flow_max_width] is also set, only
+    /// collections that satisfy both conditions are written in flow style.
+    pub fn flow_leaves(mut self, enable: bool) -> Self {
+        self.flow_leaves = enable;
+        self
+    }
+
+    /// Begin every document with `---`, including the first. Disabled by
+    /// default, in which case `---` is only written between documents, and in
+    /// front of a document that has directives.
+    pub fn explicit_start(mut self, enable: bool) -> Self {
+        self.explicit_start = enable;
+        self
+    }
+
+    /// End every document with `...`. Disabled by default.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml::{Serializer, SerializerOptions};
+    ///
+    /// let options = SerializerOptions::new()
+    ///     .explicit_start(true)
+    ///     .explicit_end(true);
+    /// let mut ser = Serializer::new(Vec::new()).with_options(options);
+    /// "first".serialize(&mut ser).unwrap();
+    /// "second".serialize(&mut ser).unwrap();
+    ///
+    /// let yaml = String::from_utf8(ser.into_inner().unwrap()).unwrap();
+    /// assert_eq!(yaml, "--- first\n...\n--- second\n...\n");
+    /// ```
+    pub fn explicit_end(mut self, enable: bool) -> Self {
+        self.explicit_end = enable;
+        self
+    }
+
+    /// Write a `%YAML` directive declaring the version of YAML in front of
+    /// every document. Only versions 1.1 and 1.2 are supported; serializing
+    /// with any other version fails.
+    ///
+    /// Directives can only follow a document that ends in `...`, so every
+    /// document but the last is ended with `...` too.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml::{Serializer, SerializerOptions};
+    ///
+    /// let options = SerializerOptions::new().version_directive(1, 2);
+    /// let mut ser = Serializer::new(Vec::new()).with_options(options);
+    /// "first".serialize(&mut ser).unwrap();
+    /// "second".serialize(&mut ser).unwrap();
+    ///
+    /// let yaml = String::from_utf8(ser.into_inner().unwrap()).unwrap();
+    /// assert_eq!(yaml, "%YAML 1.2\n--- first\n...\n%YAML 1.2\n--- second\n");
+    /// ```
+    pub fn version_directive(mut self, major: u8, minor: u8) -> Self {
+        self.version_directive = Some((major, minor));
+        self
+    }
+
+    /// Declare a tag handle with a `%TAG` directive in front of every
+    /// document, and use it to shorten tags that begin with `prefix`. May be
+    /// called more than once to declare several handles.
+    ///
+    /// The handle is either `!!` or consists of alphanumeric characters
+    /// between two `!`, like `!k8s!`. Tags are written as local tags, so the
+    /// prefix begins with `!` in order to match them. Serializing fails if
+    /// the handle is not valid.
+    ///
+    /// ```
+    /// use serde_yaml::value::{Tag, TaggedValue};
+    /// use serde_yaml::{Serializer, SerializerOptions, Value};
+    /// use serde::Serialize;
+    ///
+    /// let pod = Value::Tagged(Box::new(TaggedValue {
+    ///     tag: Tag::new("io.k8s.api.core.v1.Pod"),
+    ///     value: Value::from("web"),
+    /// }));
+    ///
+    /// let options = SerializerOptions::new().tag_directive("!k8s!", "!io.k8s.api.");
+    /// let mut ser = Serializer::new(Vec::new()).with_options(options);
+    /// pod.serialize(&mut ser).unwrap();
+    ///
+    /// let yaml = String::from_utf8(ser.into_inner().unwrap()).unwrap();
+    /// assert_eq!(yaml, "%TAG !k8s! !io.k8s.api.\n--- !k8s!core.v1.Pod web\n");
+    /// assert_eq!(serde_yaml::from_str::<Value>(&yaml).unwrap(), pod);
+    /// ```
+    pub fn tag_directive(mut self, handle: impl Into<String>, prefix: impl Into<String>) -> Self {
+        self.tag_directives.push((handle.into(), prefix.into()));
+        self
+    }
+
+    /// Write a [`TaggedValue`] whose tag is `&name` as a node with anchor
+    /// `name`, and one whose tag is `*name` as an alias to it, which is how a
+    /// [`Value`] deserialized with
+    /// [`DeserializerOptions::preserve_anchors`][crate::DeserializerOptions::preserve_anchors]
+    /// represents them. Serializing fails if an alias has a value other than
+    /// null.
+    ///
+    /// Off by default, in which case such tags are written as local tags
+    /// `!&name` and `!*name`.
+    ///
+    /// ```
+    /// use serde::{Deserialize, Serialize};
+    /// use serde_yaml::{Deserializer, DeserializerOptions, Serializer, SerializerOptions, Value};
+    ///
+    /// let yaml = "base: &base\n  image: rust\njob: *base\n";
+    ///
+    /// let options = DeserializerOptions::new().preserve_anchors(true);
+    /// let de = Deserializer::from_str(yaml).with_options(options);
+    /// let value = Value::deserialize(de).unwrap();
+    ///
+    /// let options = SerializerOptions::new().preserve_anchors(true);
+    /// let mut ser = Serializer::new(Vec::new()).with_options(options);
+    /// value.serialize(&mut ser).unwrap();
+    ///
+    /// let output = String::from_utf8(ser.into_inner().unwrap()).unwrap();
+    /// assert_eq!(output, yaml);
+    /// ```
+    ///
+    /// [`TaggedValue`]: crate::value::TaggedValue
+    /// [`Value`]: crate::Value
+    pub fn preserve_anchors(mut self, enable: bool) -> Self {
+        self.preserve_anchors = enable;
+        self
+    }
+}
+
+impl Default for SerializerOptions {
+    fn default() -> Self {
+        SerializerOptions::new()
+    }
+}
+
 impl<W> Serializer<W>
 where
     W: io::Write,
@@ -63,18 +463,47 @@ where
     /// Creates a new YAML serializer.
     pub fn new(writer: W) -> Self {
         let mut emitter = Emitter::new({
-            let writer = Box::new(writer);
+            let writer = Box::new(Output {
+                writer,
+                document: None,
+            });
             unsafe { mem::transmute::<Box<dyn io::Write>, Box<dyn io::Write>>(writer) }
         });
         emitter.emit(Event::StreamStart).unwrap();
         Serializer {
             depth: 0,
             state: State::NothingInParticular,
+            anchor: None,
+            alias: None,
+            key: false,
+            flow: false,
+            pending: Vec::new(),
+            candidates: Vec::new(),
+            options: SerializerOptions::new(),
             emitter,
             writer: PhantomData,
         }
     }
 
+    /// Replaces the layout options of this serializer.
+    ///
+    /// Documents that have already been serialized are not affected.
+    pub fn with_options(mut self, options: SerializerOptions) -> Self {
+        self.emitter.set_indent(options.indent);
+        self.emitter
+            .set_width(options.width.map_or(usize::MAX, |width| {
+                // Libyaml requires room for at least two levels of indentation.
+                cmp::max(width, options.indent * 2 + 1)
+            }));
+        if needs_relayout(&options) || has_local_tag_prefix(&options) {
+            output::<W>(&mut self.emitter)
+                .document
+                .get_or_insert_with(Vec::new);
+        }
+        self.options = options;
+        self
+    }
+
     /// Calls [`.flush()`](io::Write::flush) on the underlying `io::Write`
     /// object.
     pub fn flush(&mut self) -> Result<()> {
@@ -87,48 +516,186 @@ where
         self.emitter.emit(Event::StreamEnd)?;
         self.emitter.flush()?;
         let writer = self.emitter.into_inner();
-        Ok(*unsafe { Box::from_raw(Box::into_raw(writer).cast::<W>()) })
+        let mut output = *unsafe { Box::from_raw(Box::into_raw(writer).cast::<Output<W>>()) };
+        if let Some(rest) = output.document {
+            output
+                .writer
+                .write_all(&rest)
+                .map_err(|err| error::new(ErrorImpl::Io(err)))?;
+        }
+        Ok(output.writer)
     }
 
     fn emit_scalar(&mut self, mut scalar: Scalar) -> Result<()> {
         self.flush_mapping_start()?;
+        self.check_no_alias()?;
+        self.key = false;
         if let Some(tag) = self.take_tag() {
             scalar.tag = Some(tag);
         }
+        scalar.anchor = self.anchor.take();
+        self.flow = false;
         self.value_start()?;
-        self.emitter.emit(Event::Scalar(scalar))?;
+        self.emit(Event::Scalar(scalar))?;
         self.value_end()
     }
 
     fn emit_sequence_start(&mut self) -> Result<()> {
         self.flush_mapping_start()?;
+        self.check_no_alias()?;
+        self.key = false;
         self.value_start()?;
+        let anchor = self.anchor.take();
         let tag = self.take_tag();
-        self.emitter.emit(Event::SequenceStart(Sequence { tag }))?;
+        let style = if mem::take(&mut self.flow) {
+            CollectionStyle::Flow
+        } else {
+            CollectionStyle::Any
+        };
+        self.emit(Event::SequenceStart(Sequence { anchor, tag, style }))?;
         Ok(())
     }
 
     fn emit_sequence_end(&mut self) -> Result<()> {
-        self.emitter.emit(Event::SequenceEnd)?;
+        self.emit(Event::SequenceEnd)?;
         self.value_end()
     }
 
     fn emit_mapping_start(&mut self) -> Result<()> {
         self.flush_mapping_start()?;
+        self.check_no_alias()?;
+        self.key = false;
         self.value_start()?;
+        let anchor = self.anchor.take();
         let tag = self.take_tag();
-        self.emitter.emit(Event::MappingStart(Mapping { tag }))?;
+        let style = if mem::take(&mut self.flow) {
+            CollectionStyle::Flow
+        } else {
+            CollectionStyle::Any
+        };
+        self.emit(Event::MappingStart(Mapping { anchor, tag, style }))?;
+        Ok(())
+    }
+
+    // An alias stands in for a whole node, so the value it is attached to
+    // must be null.
+    fn check_no_alias(&mut self) -> Result<()> {
+        if self.alias.take().is_some() {
+            return Err(error::new(ErrorImpl::AliasWithValue));
+        }
         Ok(())
     }
 
     fn emit_mapping_end(&mut self) -> Result<()> {
-        self.emitter.emit(Event::MappingEnd)?;
+        self.emit(Event::MappingEnd)?;
         self.value_end()
     }
 
+    // Passes an event on to the emitter, unless it is part of a collection
+    // that might be written in flow style. Events of such a collection are
+    // held back until it either ends, at which point it is written in flow
+    // style, or it is found to be too wide or to not consist of leaves. Any
+    // collection enclosing one that is disqualified is disqualified too, so
+    // the candidates are always the innermost of the open collections.
+    fn emit(&mut self, event: Event) -> Result<()> {
+        if self.options.flow_max_width.is_none() && !self.options.flow_leaves {
+            self.emitter.emit(event)?;
+            return Ok(());
+        }
+
+        let is_node = matches!(
+            event,
+            Event::Scalar(_) | Event::Alias(_) | Event::SequenceStart(_) | Event::MappingStart(_)
+        );
+        if is_node {
+            if let Some(parent) = self.candidates.last_mut() {
+                parent.items += 1;
+                // Room for the `, ` or `: ` in front of the node.
+                let separator = if parent.items == 1 { 0 } else { 2 };
+                let width = separator + flow_width(&event);
+                for candidate in &mut self.candidates {
+                    candidate.width += width;
+                }
+            }
+        }
+        let disqualified = match &event {
+            Event::Scalar(scalar) => {
+                let block = matches!(scalar.style, ScalarStyle::Literal | ScalarStyle::Folded);
+                if block {
+                    self.candidates.len()
+                } else {
+                    self.too_wide()
+                }
+            }
+            Event::SequenceStart(_) | Event::MappingStart(_) if self.options.flow_leaves => {
+                self.candidates.len()
+            }
+            _ => self.too_wide(),
+        };
+        for candidate in self.candidates.drain(..disqualified) {
+            self.pending[candidate.start].set_style(CollectionStyle::Block);
+        }
+        let decided = self
+            .candidates
+            .first()
+            .map_or(self.pending.len(), |candidate| candidate.start);
+        for candidate in &mut self.candidates {
+            candidate.start -= decided;
+        }
+        for pending in self.pending.drain(..decided) {
+            pending.emit(&mut self.emitter)?;
+        }
+
+        match event {
+            Event::SequenceStart(_) | Event::MappingStart(_) if self.depth > 1 => {
+                self.candidates.push(Candidate {
+                    start: self.pending.len(),
+                    items: 0,
+                    width: flow_width(&event),
+                });
+                self.pending.push(Pending::new(event));
+                if self.too_wide() > 0 {
+                    self.candidates.pop();
+                    self.pending.pop().unwrap().emit(&mut self.emitter)?;
+                }
+            }
+            Event::SequenceEnd | Event::MappingEnd if !self.candidates.is_empty() => {
+                let candidate = self.candidates.pop().unwrap();
+                self.pending[candidate.start].set_style(CollectionStyle::Flow);
+                self.pending.push(Pending::new(event));
+                if self.candidates.is_empty() {
+                    for pending in mem::take(&mut self.pending) {
+                        pending.emit(&mut self.emitter)?;
+                    }
+                }
+            }
+            event if self.candidates.is_empty() => self.emitter.emit(event)?,
+            event => self.pending.push(Pending::new(event)),
+        }
+        Ok(())
+    }
+
+    // The number of candidates for flow style, counting from the outermost,
+    // that are wider than allowed. The width of a collection is at least that
+    // of any collection inside it.
+    fn too_wide(&self) -> usize {
+        match self.options.flow_max_width {
+            Some(max) => self
+                .candidates
+                .iter()
+                .take_while(|candidate| candidate.width > max)
+                .count(),
+            None => 0,
+        }
+    }
+
     fn value_start(&mut self) -> Result<()> {
         if self.depth == 0 {
-            self.emitter.emit(Event::DocumentStart)?;
+            self.emitter.emit(Event::DocumentStart(DocumentStart {
+                version: self.options.version_directive,
+                tags: self.options.tag_directives.clone(),
+                explicit: self.options.explicit_start,
+            }))?;
         }
         self.depth += 1;
         Ok(())
@@ -137,15 +704,52 @@ where
     fn value_end(&mut self) -> Result<()> {
         self.depth -= 1;
         if self.depth == 0 {
-            self.emitter.emit(Event::DocumentEnd)?;
+            self.emitter.emit(Event::DocumentEnd(DocumentEnd {
+                explicit: self.options.explicit_end,
+            }))?;
+            self.write_document()?;
         }
         Ok(())
     }
 
+    // Libyaml writes out each document as soon as it ends. If the document
+    // was held back to apply a layout that libyaml does not support, apply it
+    // now and pass the result on to the writer.
+    fn write_document(&mut self) -> Result<()> {
+        let output = output::<W>(&mut self.emitter);
+        let document = match &mut output.document {
+            Some(document) => mem::take(document),
+            None => return Ok(()),
+        };
+        let document =
+            String::from_utf8(document).map_err(|err| error::new(ErrorImpl::FromUtf8(err)))?;
+        let mut document = if needs_relayout(&self.options) {
+            // The end marker of an open-ended previous document, written by
+            // libyaml in front of this document's directives.
+            let (end_marker, document) = match document.strip_prefix("...\n") {
+                Some(rest) => ("...\n", rest),
+                None => ("", document.as_str()),
+            };
+            end_marker.to_owned() + &relayout(document, &self.options)?
+        } else {
+            document
+        };
+        if has_local_tag_prefix(&self.options) {
+            document = unescape_local_tag_prefixes(&document);
+        }
+        output
+            .writer
+            .write_all(document.as_bytes())
+            .map_err(|err| error::new(ErrorImpl::Io(err)))
+    }
+
     fn take_tag(&mut self) -> Option<String> {
         let state = mem::replace(&mut self.state, State::NothingInParticular);
         if let State::FoundTag(mut tag) = state {
-            if !tag.starts_with('!') {
+            if let Some(suffix) = tag.strip_prefix('!') {
+                // A `!!name` tag, abbreviating `tag:yaml.org,2002:name`.
+                tag = format!("tag:yaml.org,2002:{}", suffix);
+            } else {
                 tag.insert(0, '!');
             }
             Some(tag)
@@ -155,6 +759,24 @@ where
         }
     }
 
+    fn serialize_mapping_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        self.key = true;
+        let result = key.serialize(&mut *self);
+        self.key = false;
+        result
+    }
+
+    fn quote_policy(&self) -> QuotePolicy {
+        if self.key {
+            self.options.quote_keys
+        } else {
+            self.options.quote_values
+        }
+    }
+
     fn flush_mapping_start(&mut self) -> Result<()> {
         if let State::CheckForTag = self.state {
             self.state = State::NothingInParticular;
@@ -166,6 +788,300 @@ where
     }
 }
 
+pub(crate) const FLOW: &str = "$serde_yaml::private::Flow";
+
+// A sequence or mapping that may yet be written in flow style.
+struct Candidate {
+    // Index of its start event in `Serializer::pending`.
+    start: usize,
+    // Number of nodes directly inside it so far.
+    items: usize,
+    // Width so far when written in flow style.
+    width: usize,
+}
+
+// An event held back until the style of the collections around it is known.
+enum Pending {
+    Scalar {
+        anchor: Option<String>,
+        tag: Option<String>,
+        value: String,
+        style: ScalarStyle,
+    },
+    Alias(String),
+    SequenceStart(Sequence),
+    SequenceEnd,
+    MappingStart(Mapping),
+    MappingEnd,
+}
+
+impl Pending {
+    fn new(event: Event) -> Self {
+        match event {
+            Event::Scalar(scalar) => Pending::Scalar {
+                anchor: scalar.anchor,
+                tag: scalar.tag,
+                value: scalar.value.to_owned(),
+                style: scalar.style,
+            },
+            Event::Alias(alias) => Pending::Alias(alias),
+            Event::SequenceStart(sequence) => Pending::SequenceStart(sequence),
+            Event::SequenceEnd => Pending::SequenceEnd,
+            Event::MappingStart(mapping) => Pending::MappingStart(mapping),
+            Event::MappingEnd => Pending::MappingEnd,
+            Event::StreamStart
+            | Event::StreamEnd
+            | Event::DocumentStart(_)
+            | Event::DocumentEnd(_) => {
+                unreachable!()
+            }
+        }
+    }
+
+    // Styles chosen by the user through `with::flow` are left alone.
+    fn set_style(&mut self, style: CollectionStyle) {
+        match self {
+            Pending::SequenceStart(Sequence {
+                style: current @ CollectionStyle::Any,
+                ..
+            })
+            | Pending::MappingStart(Mapping {
+                style: current @ CollectionStyle::Any,
+                ..
+            }) => *current = style,
+            _ => {}
+        }
+    }
+
+    fn emit(self, emitter: &mut Emitter) -> Result<()> {
+        let event = match self {
+            Pending::Scalar {
+                anchor,
+                tag,
+                value,
+                style,
+            } => {
+                return Ok(emitter.emit(Event::Scalar(Scalar {
+                    anchor,
+                    tag,
+                    value: &value,
+                    style,
+                }))?);
+            }
+            Pending::Alias(alias) => Event::Alias(alias),
+            Pending::SequenceStart(sequence) => Event::SequenceStart(sequence),
+            Pending::SequenceEnd => Event::SequenceEnd,
+            Pending::MappingStart(mapping) => Event::MappingStart(mapping),
+            Pending::MappingEnd => Event::MappingEnd,
+        };
+        emitter.emit(event)?;
+        Ok(())
+    }
+}
+
+// Width of a node's own text in flow style, not counting anything inside it.
+fn flow_width(event: &Event) -> usize {
+    let (anchor, tag, width) = match event {
+        Event::Scalar(scalar) => {
+            let quotes = match scalar.style {
+                ScalarStyle::SingleQuoted | ScalarStyle::DoubleQuoted => 2,
+                _ => 0,
+            };
+            let width = scalar.value.chars().count() + quotes;
+            (&scalar.anchor, &scalar.tag, width)
+        }
+        Event::Alias(alias) => return 1 + alias.len(),
+        Event::SequenceStart(sequence) => (&sequence.anchor, &sequence.tag, 2),
+        Event::MappingStart(mapping) => (&mapping.anchor, &mapping.tag, 2),
+        _ => return 0,
+    };
+    // Properties are written `&anchor !tag ` in front of the node.
+    let anchor = anchor.as_ref().map_or(0, |anchor| anchor.len() + 2);
+    let tag = tag.as_ref().map_or(0, |tag| tag.len() + 1);
+    anchor + tag + width
+}
+
+// The writer passed to libyaml. While a layout that libyaml cannot produce is
+// in effect, output is collected in `document` until the end of each document.
+struct Output<W> {
+    writer: W,
+    document: Option<Vec<u8>>,
+}
+
+impl<W> io::Write for Output<W>
+where
+    W: io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.document {
+            Some(document) => document.write(buf),
+            None => self.writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn output<'a, W>(emitter: &'a mut Emitter<'static>) -> &'a mut Output<W> {
+    let writer: *mut dyn io::Write = emitter.get_mut();
+    unsafe { &mut *writer.cast::<Output<W>>() }
+}
+
+fn needs_relayout(options: &SerializerOptions) -> bool {
+    options.indent_sequences || !options.mappings_on_dash_line
+}
+
+fn has_local_tag_prefix(options: &SerializerOptions) -> bool {
+    options
+        .tag_directives
+        .iter()
+        .any(|(_handle, prefix)| prefix.starts_with('!'))
+}
+
+// Libyaml percent-encodes the '!' that begins a local tag prefix in a `%TAG`
+// directive. That is equivalent but hard to read, so write the '!' as is.
+fn unescape_local_tag_prefixes(yaml: &str) -> String {
+    let mut unescaped = String::with_capacity(yaml.len());
+    for line in yaml.split_inclusive('\n') {
+        if let Some(directive) = line.strip_prefix("%TAG ") {
+            if let Some((handle, prefix)) = directive.split_once(' ') {
+                if let Some(prefix) = prefix.strip_prefix("%21") {
+                    unescaped.push_str("%TAG ");
+                    unescaped.push_str(handle);
+                    unescaped.push_str(" !");
+                    unescaped.push_str(prefix);
+                    continue;
+                }
+            }
+        }
+        unescaped.push_str(line);
+    }
+    unescaped
+}
+
+// Rearranges a document written by libyaml according to the options that
+// libyaml has no support for. Indenting a block node's lines by the same
+// amount and moving a block mapping from the end of a dash's line to the
+// following lines preserve the meaning of the document, so these are done by
+// locating the affected nodes with the parser and editing the text.
+fn relayout(yaml: &str, options: &SerializerOptions) -> Result<String> {
+    enum Frame {
+        BlockMapping {
+            next_is_key: bool,
+            complex_key: bool,
+        },
+        BlockSequence {
+            indented: bool,
+            first_item: Option<Location>,
+        },
+        Flow,
+    }
+
+    // Ranges of 1-based line numbers to shift right by one level of indent,
+    // and byte offsets at which to break a line.
+    let mut shifts = Vec::new();
+    let mut breaks = Vec::new();
+
+    let mut stack = Vec::new();
+    for event in event::Parser::from_str(yaml) {
+        let event = event?;
+        let start = event.start();
+        let (is_value, is_item) = match (stack.last_mut(), event.get_ref()) {
+            (_, event::Event::SequenceEnd | event::Event::MappingEnd) => (false, false),
+            (
+                Some(Frame::BlockMapping {
+                    next_is_key,
+                    complex_key,
+                }),
+                _,
+            ) => {
+                *next_is_key = !*next_is_key;
+                if !*next_is_key {
+                    // Libyaml writes a key that is a collection, or too long
+                    // for a simple key, after a `?` indicator, and the value
+                    // of such a key on the line of the `: ` in front of it.
+                    let line_start = yaml[..start.index()].rfind('\n').map_or(0, |i| i + 1);
+                    *complex_key = yaml[line_start..start.index()].trim_end().ends_with('?');
+                }
+                (*next_is_key && !*complex_key, false)
+            }
+            (Some(Frame::BlockSequence { first_item, .. }), _) => {
+                first_item.get_or_insert(start);
+                (false, true)
+            }
+            (Some(Frame::Flow) | None, _) => (false, false),
+        };
+        match event.into_inner() {
+            event::Event::SequenceStart(sequence) => {
+                stack.push(if sequence.style == event::CollectionStyle::Flow {
+                    Frame::Flow
+                } else {
+                    Frame::BlockSequence {
+                        indented: is_value && options.indent_sequences,
+                        first_item: None,
+                    }
+                });
+            }
+            event::Event::MappingStart(mapping) => {
+                stack.push(if mapping.style == event::CollectionStyle::Flow {
+                    Frame::Flow
+                } else {
+                    if is_item
+                        && !options.mappings_on_dash_line
+                        && mapping.anchor.is_none()
+                        && mapping.tag.is_none()
+                    {
+                        breaks.push(start.index());
+                    }
+                    Frame::BlockMapping {
+                        next_is_key: true,
+                        complex_key: false,
+                    }
+                });
+            }
+            event::Event::SequenceEnd | event::Event::MappingEnd => {
+                if let Some(Frame::BlockSequence {
+                    indented: true,
+                    first_item: Some(first_item),
+                }) = stack.pop()
+                {
+                    shifts.push(first_item.line()..start.line());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut relaid = String::with_capacity(yaml.len());
+    let mut line_start = 0;
+    for (row, line) in yaml.split_inclusive('\n').enumerate() {
+        let line_end = line_start + line.len();
+        let depth = shifts
+            .iter()
+            .filter(|rows| rows.contains(&(row + 1)))
+            .count();
+        let mut indent = depth * options.indent;
+        let mut from = line_start;
+        for &at in &breaks {
+            if line_start < at && at < line_end {
+                relaid.extend(iter::repeat(' ').take(indent));
+                relaid.push_str(yaml[from..at].trim_end());
+                relaid.push('\n');
+                indent = depth * options.indent + at - line_start;
+                from = at;
+            }
+        }
+        if !yaml[from..line_end].trim().is_empty() {
+            relaid.extend(iter::repeat(' ').take(indent));
+        }
+        relaid.push_str(&yaml[from..line_end]);
+        line_start = line_end;
+    }
+    Ok(relaid)
+}
+
 impl<'a, W> ser::Serializer for &'a mut Serializer<W>
 where
     W: io::Write,
@@ -183,6 +1099,7 @@ where
 
     fn serialize_bool(self, v: bool) -> Result<()> {
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: if v { "true" } else { "false" },
             style: ScalarStyle::Plain,
@@ -191,6 +1108,7 @@ where
 
     fn serialize_i8(self, v: i8) -> Result<()> {
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: itoa::Buffer::new().format(v),
             style: ScalarStyle::Plain,
@@ -199,6 +1117,7 @@ where
 
     fn serialize_i16(self, v: i16) -> Result<()> {
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: itoa::Buffer::new().format(v),
             style: ScalarStyle::Plain,
@@ -207,6 +1126,7 @@ where
 
     fn serialize_i32(self, v: i32) -> Result<()> {
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: itoa::Buffer::new().format(v),
             style: ScalarStyle::Plain,
@@ -215,6 +1135,7 @@ where
 
     fn serialize_i64(self, v: i64) -> Result<()> {
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: itoa::Buffer::new().format(v),
             style: ScalarStyle::Plain,
@@ -223,6 +1144,7 @@ where
 
     fn serialize_i128(self, v: i128) -> Result<()> {
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: itoa::Buffer::new().format(v),
             style: ScalarStyle::Plain,
@@ -231,6 +1153,7 @@ where
 
     fn serialize_u8(self, v: u8) -> Result<()> {
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: itoa::Buffer::new().format(v),
             style: ScalarStyle::Plain,
@@ -239,6 +1162,7 @@ where
 
     fn serialize_u16(self, v: u16) -> Result<()> {
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: itoa::Buffer::new().format(v),
             style: ScalarStyle::Plain,
@@ -247,6 +1171,7 @@ where
 
     fn serialize_u32(self, v: u32) -> Result<()> {
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: itoa::Buffer::new().format(v),
             style: ScalarStyle::Plain,
@@ -255,6 +1180,7 @@ where
 
     fn serialize_u64(self, v: u64) -> Result<()> {
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: itoa::Buffer::new().format(v),
             style: ScalarStyle::Plain,
@@ -263,6 +1189,7 @@ where
 
     fn serialize_u128(self, v: u128) -> Result<()> {
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: itoa::Buffer::new().format(v),
             style: ScalarStyle::Plain,
@@ -272,6 +1199,7 @@ where
     fn serialize_f32(self, v: f32) -> Result<()> {
         let mut buffer = ryu::Buffer::new();
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: match v.classify() {
                 num::FpCategory::Infinite if v.is_sign_positive() => ".inf",
@@ -286,6 +1214,7 @@ where
     fn serialize_f64(self, v: f64) -> Result<()> {
         let mut buffer = ryu::Buffer::new();
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: match v.classify() {
                 num::FpCategory::Infinite if v.is_sign_positive() => ".inf",
@@ -298,7 +1227,11 @@ where
     }
 
     fn serialize_char(self, value: char) -> Result<()> {
+        if self.quote_policy() == QuotePolicy::AlwaysDouble {
+            return self.serialize_str(value.encode_utf8(&mut [0u8; 4]));
+        }
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: value.encode_utf8(&mut [0u8; 4]),
             style: ScalarStyle::SingleQuoted,
@@ -352,31 +1285,85 @@ where
             }
         }
 
-        let style = if value.contains('\n') {
+        let policy = self.quote_policy();
+        let style = if policy == QuotePolicy::AlwaysSingle {
+            ScalarStyle::SingleQuoted
+        } else if policy == QuotePolicy::AlwaysDouble {
+            ScalarStyle::DoubleQuoted
+        } else if value.contains('\n') {
             ScalarStyle::Literal
+        } else if self
+            .options
+            .width
+            .map_or(false, |width| value.chars().count() > width)
+            && !value.starts_with(' ')
+            && !value.contains('\t')
+        {
+            // A line of a folded scalar that starts with whitespace keeps the
+            // line breaks around it, so any string whose text libyaml might
+            // put at the start of a line after a space is left to libyaml to
+            // quote. Libyaml also falls back to a double-quoted scalar, folded
+            // the same way, if the string cannot be written as a block scalar.
+            ScalarStyle::Folded
         } else {
-            let result = crate::de::visit_untagged_scalar(
-                InferScalarStyle,
-                value,
-                None,
-                libyaml::parser::ScalarStyle::Plain,
-            );
-            result.unwrap_or(ScalarStyle::Any)
+            // Quote anything that a reader using the YAML 1.2 rules, or also
+            // the YAML 1.1 rules if asked to, would not take to be a string.
+            let schemas: &[Schema] = if policy == QuotePolicy::WhenAmbiguous {
+                &[Schema::Core, Schema::Yaml11]
+            } else {
+                &[Schema::Core]
+            };
+            let mut style = ScalarStyle::Any;
+            for &schema in schemas {
+                let result = crate::de::visit_untagged_scalar(
+                    InferScalarStyle,
+                    value,
+                    None,
+                    libyaml::parser::ScalarStyle::Plain,
+                    schema,
+                );
+                if let Ok(ScalarStyle::SingleQuoted) = result {
+                    style = ScalarStyle::SingleQuoted;
+                }
+            }
+            style
         };
 
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value,
             style,
         })
     }
 
-    fn serialize_bytes(self, _value: &[u8]) -> Result<()> {
-        Err(error::new(ErrorImpl::BytesUnsupported))
+    fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        let encoded = base64::encode_lines(value);
+        let style = if encoded.is_empty() {
+            ScalarStyle::SingleQuoted
+        } else if encoded.contains('\n') {
+            ScalarStyle::Literal
+        } else {
+            ScalarStyle::Plain
+        };
+        self.emit_scalar(Scalar {
+            anchor: None,
+            tag: Some(Tag::BINARY.to_owned()),
+            value: &encoded,
+            style,
+        })
     }
 
     fn serialize_unit(self) -> Result<()> {
+        if !matches!(self.state, State::FoundTag(_)) {
+            if let Some(alias) = self.alias.take() {
+                self.value_start()?;
+                self.emit(Event::Alias(alias))?;
+                return self.value_end();
+            }
+        }
         self.emit_scalar(Scalar {
+            anchor: None,
             tag: None,
             value: "null",
             style: ScalarStyle::Plain,
@@ -396,10 +1383,13 @@ where
         self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + ser::Serialize,
     {
+        if name == FLOW {
+            self.flow = true;
+        }
         value.serialize(self)
     }
 
@@ -611,7 +1601,7 @@ where
         T: ?Sized + ser::Serialize,
     {
         self.flush_mapping_start()?;
-        key.serialize(&mut **self)
+        (**self).serialize_mapping_key(key)
     }
 
     fn serialize_value<T>(&mut self, value: &T) -> Result<()>
@@ -626,8 +1616,19 @@ where
         K: ?Sized + ser::Serialize,
         V: ?Sized + ser::Serialize,
     {
-        key.serialize(&mut **self)?;
+        (**self).serialize_mapping_key(key)?;
         let tagged = matches!(self.state, State::FoundTag(_));
+        if let (State::FoundTag(tag), true) = (&self.state, self.options.preserve_anchors) {
+            // Anchors and aliases preserved in a Value look like tags `&name`
+            // and `*name`.
+            if let Some(anchor) = tag.strip_prefix('&') {
+                self.anchor = Some(anchor.to_owned());
+                self.state = State::NothingInParticular;
+            } else if let Some(alias) = tag.strip_prefix('*') {
+                self.alias = Some(alias.to_owned());
+                self.state = State::NothingInParticular;
+            }
+        }
         value.serialize(&mut **self)?;
         if tagged {
             self.state = State::AlreadyTagged;
@@ -658,7 +1659,7 @@ where
     where
         V: ?Sized + ser::Serialize,
     {
-        self.serialize_str(key)?;
+        (**self).serialize_mapping_key(key)?;
         value.serialize(&mut **self)
     }
 
@@ -678,7 +1679,7 @@ where
     where
         V: ?Sized + ser::Serialize,
     {
-        self.serialize_str(field)?;
+        (**self).serialize_mapping_key(field)?;
         v.serialize(&mut **self)
     }
 
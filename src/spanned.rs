@@ -0,0 +1,216 @@
+//! A value together with its location in the YAML input.
+
+use crate::error::Location;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+pub(crate) const NAME: &str = "$serde_yaml::private::Spanned";
+pub(crate) const START: &str = "$serde_yaml::private::Spanned::start";
+pub(crate) const END: &str = "$serde_yaml::private::Spanned::end";
+pub(crate) const VALUE: &str = "$serde_yaml::private::Spanned::value";
+pub(crate) const FIELDS: &[&str] = &[START, END, VALUE];
+
+/// A value of type `T` along with the location in the input of the YAML node
+/// it was deserialized from.
+///
+/// The location is only available when deserializing from YAML text through
+/// [`serde_yaml::Deserializer`][crate::Deserializer] or one of the `from_*`
+/// functions. Deserializing a `Spanned<T>` from a [`Value`][crate::Value]
+/// fails, as a `Value` does not keep track of where it came from.
+///
+/// Comparisons, hashing and serialization only involve the value; the
+/// location is disregarded.
+///
+/// The same type pairs each event from an [`event::Parser`] with its location.
+///
+/// ```
+/// # use serde_derive::Deserialize;
+/// use serde::Deserialize;
+/// use serde_yaml::Spanned;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     name: String,
+///     replicas: Spanned<u32>,
+/// }
+///
+/// let yaml = "name: web\nreplicas: 0\n";
+/// let config: Config = serde_yaml::from_str(yaml).unwrap();
+///
+/// if *config.replicas.get_ref() == 0 {
+///     let start = config.replicas.start();
+///     assert_eq!(start.line(), 2);
+///     assert_eq!(start.column(), 11);
+/// }
+/// ```
+///
+/// [`event::Parser`]: crate::event::Parser
+#[derive(Clone, Debug)]
+pub struct Spanned<T> {
+    start: Location,
+    end: Location,
+    value: T,
+}
+
+impl<T> Spanned<T> {
+    pub(crate) fn new(start: Location, end: Location, value: T) -> Self {
+        Spanned { start, end, value }
+    }
+
+    /// Location of the first character of the node.
+    pub fn start(&self) -> Location {
+        self.start
+    }
+
+    /// Location immediately past the last character of the node.
+    pub fn end(&self) -> Location {
+        self.end
+    }
+
+    /// Borrows the value.
+    pub fn get_ref(&self) -> &T {
+        &self.value
+    }
+
+    /// Mutably borrows the value.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
+    /// Discards the location and returns the value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl Borrow<str> for Spanned<String> {
+    fn borrow(&self) -> &str {
+        &self.value
+    }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: Eq> Eq for Spanned<T> {}
+
+impl<T: PartialOrd> PartialOrd for Spanned<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<T: Ord> Ord for Spanned<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+
+impl<T: Hash> Hash for Spanned<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+impl<T: Serialize> Serialize for Spanned<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.value.serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Spanned<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct SpannedVisitor<T>(std::marker::PhantomData<T>);
+
+        impl<'de, T> Visitor<'de> for SpannedVisitor<T>
+        where
+            T: Deserialize<'de>,
+        {
+            type Value = Spanned<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a YAML node with location information")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                if map.next_key::<SpannedKey>()? != Some(SpannedKey::Start) {
+                    return Err(de::Error::custom("spanned start key not found"));
+                }
+                let start = map.next_value::<(usize, usize, usize)>()?;
+                if map.next_key::<SpannedKey>()? != Some(SpannedKey::End) {
+                    return Err(de::Error::custom("spanned end key not found"));
+                }
+                let end = map.next_value::<(usize, usize, usize)>()?;
+                if map.next_key::<SpannedKey>()? != Some(SpannedKey::Value) {
+                    return Err(de::Error::custom("spanned value key not found"));
+                }
+                let value = map.next_value()?;
+                Ok(Spanned {
+                    start: Location::new(start.0, start.1, start.2),
+                    end: Location::new(end.0, end.1, end.2),
+                    value,
+                })
+            }
+        }
+
+        let visitor = SpannedVisitor(std::marker::PhantomData);
+        deserializer.deserialize_struct(NAME, FIELDS, visitor)
+    }
+}
+
+#[derive(PartialEq)]
+enum SpannedKey {
+    Start,
+    End,
+    Value,
+}
+
+impl<'de> Deserialize<'de> for SpannedKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct KeyVisitor;
+
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = SpannedKey;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a spanned field")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                match s {
+                    START => Ok(SpannedKey::Start),
+                    END => Ok(SpannedKey::End),
+                    VALUE => Ok(SpannedKey::Value),
+                    _ => Err(de::Error::invalid_value(de::Unexpected::Str(s), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_identifier(KeyVisitor)
+    }
+}
@@ -0,0 +1,187 @@
+//! A point in time written in the YAML `!!timestamp` format.
+
+use crate::error::{self, Error, ErrorImpl};
+use serde::de::{self, Deserialize, Deserializer, Unexpected, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// A date, or a date and time of day, in the format of the YAML
+/// [`!!timestamp`] type.
+///
+/// Accepted forms include `2001-12-14`, `2001-12-14t21:59:43.10-05:00`,
+/// `2001-12-14 21:59:43.10 -5` and `2001-12-15 2:59:43.10`. A `Timestamp` is
+/// deserialized from any string in one of these forms, failing if it is not a
+/// valid date and time, and is serialized as an unquoted scalar in the
+/// canonical form `2001-12-14T21:59:43.1-05:00`.
+///
+/// ```
+/// # use serde_derive::Deserialize;
+/// use serde::Deserialize;
+/// use serde_yaml::Timestamp;
+///
+/// #[derive(Deserialize)]
+/// struct Release {
+///     published: Timestamp,
+/// }
+///
+/// let yaml = "published: 2001-12-14 21:59:43.10 -5\n";
+/// let release: Release = serde_yaml::from_str(yaml).unwrap();
+/// assert_eq!(release.published.day(), 14);
+/// assert_eq!(release.published.utc_offset(), Some(-5 * 3600));
+/// assert_eq!(release.published.to_string(), "2001-12-14T21:59:43.1-05:00");
+///
+/// assert!(serde_yaml::from_str::<Release>("published: 2001-02-29\n").is_err());
+/// ```
+///
+/// When deserializing a [`Value`][crate::Value], explicitly tagged
+/// `!!timestamp` scalars are represented as a [`TaggedValue`] with tag
+/// `!!timestamp` whose value is the string. Plain scalars in this format are
+/// strings, whatever the [`Schema`][crate::Schema], so that they fit any type
+/// that accepts a string.
+///
+/// [`!!timestamp`]: https://yaml.org/type/timestamp.html
+/// [`TaggedValue`]: crate::value::TaggedValue
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Timestamp {
+    pub(crate) year: u16,
+    pub(crate) month: u8,
+    pub(crate) day: u8,
+    pub(crate) time: Option<Time>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct Time {
+    pub(crate) hour: u8,
+    pub(crate) minute: u8,
+    pub(crate) second: u8,
+    pub(crate) nanosecond: u32,
+    pub(crate) utc_offset: Option<i32>,
+}
+
+impl Timestamp {
+    /// The year, from 0 to 9999.
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// The month, from 1 to 12.
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// The day of the month, from 1 to 31.
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// Whether a time of day was given, as opposed to only a date.
+    pub fn has_time(&self) -> bool {
+        self.time.is_some()
+    }
+
+    /// The hour, from 0 to 23. Zero if only a date was given.
+    pub fn hour(&self) -> u8 {
+        self.time.map_or(0, |time| time.hour)
+    }
+
+    /// The minute, from 0 to 59. Zero if only a date was given.
+    pub fn minute(&self) -> u8 {
+        self.time.map_or(0, |time| time.minute)
+    }
+
+    /// The second, from 0 to 60 to allow for leap seconds. Zero if only a
+    /// date was given.
+    pub fn second(&self) -> u8 {
+        self.time.map_or(0, |time| time.second)
+    }
+
+    /// The fraction of a second, in nanoseconds. Digits beyond nanosecond
+    /// precision are discarded.
+    pub fn nanosecond(&self) -> u32 {
+        self.time.map_or(0, |time| time.nanosecond)
+    }
+
+    /// The offset from UTC in seconds, positive east of Greenwich. `Some(0)`
+    /// for a time given in UTC with `Z`, and `None` if there is no time zone,
+    /// which YAML interprets as UTC.
+    pub fn utc_offset(&self) -> Option<i32> {
+        self.time.and_then(|time| time.utc_offset)
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{:04}-{:02}-{:02}",
+            self.year, self.month, self.day,
+        )?;
+        let time = match self.time {
+            Some(time) => time,
+            None => return Ok(()),
+        };
+        write!(
+            formatter,
+            "T{:02}:{:02}:{:02}",
+            time.hour, time.minute, time.second,
+        )?;
+        if time.nanosecond != 0 {
+            let fraction = format!("{:09}", time.nanosecond);
+            write!(formatter, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        match time.utc_offset {
+            None => Ok(()),
+            Some(0) => formatter.write_str("Z"),
+            Some(offset) => {
+                let sign = if offset < 0 { '-' } else { '+' };
+                let minutes = offset.unsigned_abs() / 60;
+                write!(formatter, "{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+            }
+        }
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::de::parse_timestamp(s).ok_or_else(|| error::new(ErrorImpl::FailedToParseTimestamp))
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TimestampVisitor;
+
+        impl<'de> Visitor<'de> for TimestampVisitor {
+            type Value = Timestamp;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a YAML timestamp")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                crate::de::parse_timestamp(s)
+                    .ok_or_else(|| de::Error::invalid_value(Unexpected::Str(s), &self))
+            }
+        }
+
+        deserializer.deserialize_str(TimestampVisitor)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::base64;
 use crate::value::tagged::{self, TagStringVisitor};
 use crate::value::TaggedValue;
 use crate::{number, Error, Mapping, Sequence, Value};
@@ -192,6 +193,13 @@ where
     }
 }
 
+fn visit_binary_seq<'de, V>(bytes: Vec<u8>, visitor: V) -> Result<V::Value, Error>
+where
+    V: Visitor<'de>,
+{
+    visitor.visit_seq(de::value::SeqDeserializer::new(bytes.into_iter()))
+}
+
 impl<'de> Deserializer<'de> for Value {
     type Error = Error;
 
@@ -339,6 +347,9 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
+        if let Some(bytes) = self.binary() {
+            return visitor.visit_byte_buf(bytes?);
+        }
         match self.untag() {
             Value::String(v) => visitor.visit_string(v),
             Value::Sequence(v) => visit_sequence(v, visitor),
@@ -388,6 +399,9 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
+        if let Some(bytes) = self.binary() {
+            return visit_binary_seq(bytes?, visitor);
+        }
         match self.untag() {
             Value::Sequence(v) => visit_sequence(v, visitor),
             Value::Null => visit_sequence(Sequence::new(), visitor),
@@ -849,6 +863,9 @@ impl<'de> Deserializer<'de> for &'de Value {
     where
         V: Visitor<'de>,
     {
+        if let Some(bytes) = self.binary() {
+            return visitor.visit_byte_buf(bytes?);
+        }
         match self.untag_ref() {
             Value::String(v) => visitor.visit_borrowed_str(v),
             Value::Sequence(v) => visit_sequence_ref(v, visitor),
@@ -906,6 +923,9 @@ impl<'de> Deserializer<'de> for &'de Value {
         V: Visitor<'de>,
     {
         static EMPTY: Sequence = Sequence::new();
+        if let Some(bytes) = self.binary() {
+            return visit_binary_seq(bytes?, visitor);
+        }
         match self.untag_ref() {
             Value::Sequence(v) => visit_sequence_ref(v, visitor),
             Value::Null => visit_sequence_ref(&EMPTY, visitor),
@@ -1219,6 +1239,21 @@ impl<'de> Deserializer<'de> for MapRefDeserializer<'de> {
 }
 
 impl Value {
+    // The decoded contents of a `!!binary` value, or None for any other value.
+    fn binary(&self) -> Option<Result<Vec<u8>, Error>> {
+        let tagged = match self {
+            Value::Tagged(tagged) if tagged.tag == tagged::BINARY => tagged,
+            _ => return None,
+        };
+        let exp = &"base64-encoded binary data";
+        Some(match &tagged.value {
+            Value::String(s) => {
+                base64::decode(s).ok_or_else(|| de::Error::invalid_value(Unexpected::Str(s), exp))
+            }
+            other => Err(other.invalid_type(exp)),
+        })
+    }
+
     #[cold]
     fn invalid_type<E>(&self, exp: &dyn Expected) -> E
     where
@@ -1,3 +1,4 @@
+use crate::base64;
 use crate::error::{self, Error, ErrorImpl};
 use crate::value::tagged::{self, MaybeTag};
 use crate::value::{to_value, Mapping, Number, Sequence, Tag, TaggedValue, Value};
@@ -136,11 +137,10 @@ impl ser::Serializer for Serializer {
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Value> {
-        let vec = value
-            .iter()
-            .map(|&b| Value::Number(Number::from(b)))
-            .collect();
-        Ok(Value::Sequence(vec))
+        Ok(Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(tagged::BINARY),
+            value: Value::String(base64::encode_lines(value)),
+        })))
     }
 
     fn serialize_unit(self) -> Result<Value> {
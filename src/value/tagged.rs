@@ -12,6 +12,12 @@ use std::fmt::{self, Debug, Display};
 use std::hash::{Hash, Hasher};
 use std::mem;
 
+/// Tag of `Value`s holding base64-encoded bytes.
+pub(crate) const BINARY: &str = "!!binary";
+
+/// Tag of `Value`s holding a timestamp string.
+pub(crate) const TIMESTAMP: &str = "!!timestamp";
+
 /// A representation of YAML's `!Tag` syntax, used for enums.
 ///
 /// Refer to the example code on [`TaggedValue`] for an example of deserializing
@@ -47,6 +53,11 @@ pub struct Tag {
 /// // The leading '!' in tags are not significant. The following is also true.
 /// assert!(data["scalar"].tag == "!Thing");
 /// ```
+///
+/// With [`DeserializerOptions::preserve_anchors`], anchors and aliases are also
+/// represented as tagged values, with tags `&name` and `*name`.
+///
+/// [`DeserializerOptions::preserve_anchors`]: crate::DeserializerOptions::preserve_anchors
 #[derive(Clone, PartialEq, PartialOrd, Hash, Debug)]
 pub struct TaggedValue {
     #[allow(missing_docs)]
@@ -2122,3 +2122,152 @@ pub mod singleton_map_recursive {
         }
     }
 }
+
+/// Serialize/deserialize bytes as a `!!binary` scalar holding base64.
+///
+/// Serde represents `Vec<u8>` as a sequence of integers. This module instead
+/// serializes any `AsRef<[u8]>` with `serialize_bytes`, which the YAML
+/// serializer writes out as a `!!binary` scalar. Deserialization accepts
+/// `!!binary` scalars, in which the base64 may be split across lines, as well
+/// as sequences of integers.
+///
+/// # Example
+///
+/// ```
+/// # use serde_derive::{Deserialize, Serialize};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Secret {
+///     name: String,
+///     #[serde(with = "serde_yaml::with::binary")]
+///     data: Vec<u8>,
+/// }
+///
+/// fn main() {
+///     let secret = Secret {
+///         name: "token".to_owned(),
+///         data: b"hunter2".to_vec(),
+///     };
+///
+///     let yaml = serde_yaml::to_string(&secret).unwrap();
+///     assert_eq!(yaml, "name: token\ndata: !!binary aHVudGVyMg==\n");
+///
+///     let deserialized: Secret = serde_yaml::from_str(&yaml).unwrap();
+///     assert_eq!(secret, deserialized);
+/// }
+/// ```
+pub mod binary {
+    use serde::de::{self, Deserializer, SeqAccess, Visitor};
+    use serde::ser::Serializer;
+    use std::fmt;
+
+    #[allow(missing_docs)]
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: ?Sized + AsRef<[u8]>,
+        S: Serializer,
+    {
+        serializer.serialize_bytes(value.as_ref())
+    }
+
+    #[allow(missing_docs)]
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: From<Vec<u8>>,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_byte_buf(BytesVisitor).map(T::from)
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("binary data")
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(v.to_vec())
+        }
+
+        fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(v)
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+}
+
+/// Serialize a sequence or mapping in flow style, like `[80, 443]` or
+/// `{x: 1, y: 2}`, instead of with each element on a line of its own.
+///
+/// Everything nested inside the collection is written in flow style too.
+/// Deserialization is unaffected, as is serialization to formats other than
+/// YAML.
+///
+/// # Example
+///
+/// ```
+/// # use serde_derive::{Deserialize, Serialize};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Service {
+///     name: String,
+///     #[serde(with = "serde_yaml::with::flow")]
+///     ports: Vec<u16>,
+/// }
+///
+/// fn main() {
+///     let service = Service {
+///         name: "web".to_owned(),
+///         ports: vec![80, 443],
+///     };
+///
+///     let yaml = serde_yaml::to_string(&service).unwrap();
+///     assert_eq!(yaml, "name: web\nports: [80, 443]\n");
+///
+///     let deserialized: Service = serde_yaml::from_str(&yaml).unwrap();
+///     assert_eq!(service, deserialized);
+/// }
+/// ```
+pub mod flow {
+    use serde::de::{Deserialize, Deserializer};
+    use serde::ser::{Serialize, Serializer};
+
+    #[allow(missing_docs)]
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: ?Sized + Serialize,
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(crate::ser::FLOW, value)
+    }
+
+    #[allow(missing_docs)]
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        T::deserialize(deserializer)
+    }
+}
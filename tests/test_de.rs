@@ -7,8 +7,18 @@
 )]
 
 use indoc::indoc;
+use serde::de::IgnoredAny;
 use serde_derive::Deserialize;
-use serde_yaml::{Deserializer, Number, Value};
+use serde_yaml::event::{
+    self, CollectionStyle, Event, MappingStart, Parser, ScalarStyle, SequenceStart,
+};
+use serde_yaml::node::NodeValue;
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::{
+    Deserializer, DeserializerOptions, DuplicateKeyPolicy, Mapping, Node, Number, Schema, Spanned,
+    Timestamp, Value,
+};
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fmt::Debug;
 
@@ -715,3 +725,639 @@ fn test_parse_number() {
     let err = " 1 ".parse::<Number>().unwrap_err();
     assert_eq!(err.to_string(), "failed to parse YAML number");
 }
+
+#[test]
+fn test_stream_from_reader() {
+    // An endless stream of documents. Each document must be available as
+    // soon as it is complete, without waiting for the end of the input.
+    struct Endless {
+        next: usize,
+        buffer: Vec<u8>,
+    }
+
+    impl std::io::Read for Endless {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.buffer.is_empty() {
+                self.buffer = format!("---\nk: {}\n", self.next).into_bytes();
+                self.next += 1;
+            }
+            let n = buf.len().min(self.buffer.len());
+            buf[..n].copy_from_slice(&self.buffer[..n]);
+            self.buffer.drain(..n);
+            Ok(n)
+        }
+    }
+
+    let reader = Endless {
+        next: 0,
+        buffer: Vec::new(),
+    };
+    let mut documents = Vec::new();
+    for document in Deserializer::from_reader(reader).take(3) {
+        let map: BTreeMap<String, usize> = serde::Deserialize::deserialize(document).unwrap();
+        documents.push(map["k"]);
+    }
+    assert_eq!(documents, [0, 1, 2]);
+}
+
+#[test]
+fn test_deserializer_options() {
+    let depth = 200;
+    let yaml = "[".repeat(depth) + &"]".repeat(depth);
+    let options = DeserializerOptions::new().max_depth(depth);
+    let de = Deserializer::from_str(&yaml).with_options(options);
+    let value: Value = serde::Deserialize::deserialize(de).unwrap();
+
+    let mut expected = Value::Sequence(Vec::new());
+    for _ in 1..depth {
+        expected = Value::Sequence(vec![expected]);
+    }
+    assert_eq!(expected, value);
+}
+
+#[test]
+fn test_duplicate_key_policy() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Struct {
+        x: usize,
+        y: usize,
+    }
+
+    let yaml = indoc! {"
+        x: 1
+        y: 2
+        x: 3
+    "};
+
+    let options = DeserializerOptions::new().duplicate_keys(DuplicateKeyPolicy::FirstWins);
+    let de = Deserializer::from_str(yaml).with_options(options.clone());
+    let map: BTreeMap<String, usize> = serde::Deserialize::deserialize(de).unwrap();
+    assert_eq!(map["x"], 1);
+    assert_eq!(map["y"], 2);
+    let de = Deserializer::from_str(yaml).with_options(options);
+    let deserialized: Struct = serde::Deserialize::deserialize(de).unwrap();
+    assert_eq!(Struct { x: 1, y: 2 }, deserialized);
+
+    let options = DeserializerOptions::new().duplicate_keys(DuplicateKeyPolicy::LastWins);
+    let de = Deserializer::from_str(yaml).with_options(options.clone());
+    let value: Value = serde::Deserialize::deserialize(de).unwrap();
+    assert_eq!(value["x"], 3);
+    assert_eq!(value["y"], 2);
+    let de = Deserializer::from_str(yaml).with_options(options);
+    let deserialized: Struct = serde::Deserialize::deserialize(de).unwrap();
+    assert_eq!(Struct { x: 3, y: 2 }, deserialized);
+}
+
+#[test]
+fn test_merge_keys() {
+    // From https://yaml.org/type/merge.html.
+    let yaml = indoc! {"
+        ---
+        - &CENTER { x: 1, y: 2 }
+        - &LEFT { x: 0, y: 2 }
+        - &BIG { r: 10 }
+        - &SMALL { r: 1 }
+
+        # All the following maps are equal:
+
+        - # Explicit keys
+          x: 1
+          y: 2
+          r: 10
+          label: center/big
+
+        - # Merge one map
+          << : *CENTER
+          r: 10
+          label: center/big
+
+        - # Merge multiple maps
+          << : [ *CENTER, *BIG ]
+          label: center/big
+
+        - # Override
+          << : [ *BIG, *LEFT, *SMALL ]
+          x: 1
+          label: center/big
+    "};
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(deny_unknown_fields)]
+    struct Point {
+        x: i32,
+        y: i32,
+        r: i32,
+        label: String,
+    }
+
+    let options = DeserializerOptions::new().merge_keys(true);
+    let de = Deserializer::from_str(yaml).with_options(options.clone());
+    let value: Vec<Value> = serde::Deserialize::deserialize(de).unwrap();
+    let de = Deserializer::from_str(yaml).with_options(options);
+    let points: (
+        IgnoredAny,
+        IgnoredAny,
+        IgnoredAny,
+        IgnoredAny,
+        Point,
+        Point,
+        Point,
+        Point,
+    ) = serde::Deserialize::deserialize(de).unwrap();
+
+    let expected = Point {
+        x: 1,
+        y: 2,
+        r: 10,
+        label: "center/big".to_owned(),
+    };
+    for i in 5..=7 {
+        assert_eq!(value[4], value[i]);
+    }
+    assert_eq!(expected, points.4);
+    assert_eq!(expected, points.5);
+    assert_eq!(expected, points.6);
+    assert_eq!(expected, points.7);
+}
+
+#[test]
+fn test_schema_yaml11() {
+    let yaml = indoc! {"
+        - yes
+        - Off
+        - y
+        - 0777
+        - -0b1010
+        - 0x_FF
+        - 1_000
+        - 1:30
+        - -1:30:00
+        - 0o17
+        - 1_000.5
+        - 1:30.5
+        - 1e3
+        - 1.0e+3
+        - .inf
+        - ~
+    "};
+
+    let expected = vec![
+        Value::Bool(true),
+        Value::Bool(false),
+        Value::String("y".to_owned()),
+        Value::Number(511.into()),
+        Value::Number((-10).into()),
+        Value::Number(255.into()),
+        Value::Number(1000.into()),
+        Value::Number(90.into()),
+        Value::Number((-5400).into()),
+        Value::String("0o17".to_owned()),
+        Value::Number(1000.5.into()),
+        Value::Number(90.5.into()),
+        Value::String("1e3".to_owned()),
+        Value::Number(1000.0.into()),
+        Value::Number(f64::INFINITY.into()),
+        Value::Null,
+    ];
+
+    let options = DeserializerOptions::new().schema(Schema::Yaml11);
+    let de = Deserializer::from_str(yaml).with_options(options.clone());
+    let value: Value = serde::Deserialize::deserialize(de).unwrap();
+    assert_eq!(Value::Sequence(expected), value);
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Typed {
+        enabled: bool,
+        mode: u32,
+        timeout: i64,
+    }
+
+    let yaml = indoc! {"
+        enabled: on
+        mode: 0644
+        timeout: 1:00:00
+    "};
+    let expected = Typed {
+        enabled: true,
+        mode: 0o644,
+        timeout: 3600,
+    };
+    let de = Deserializer::from_str(yaml).with_options(options);
+    let deserialized: Typed = serde::Deserialize::deserialize(de).unwrap();
+    assert_eq!(expected, deserialized);
+}
+
+#[test]
+fn test_schema_json() {
+    let yaml = indoc! {"
+        - null
+        - ~
+        - true
+        - True
+        - -12
+        - 012
+        - 0x1F
+        - 1.5e3
+        - .inf
+    "};
+
+    let expected = vec![
+        Value::Null,
+        Value::String("~".to_owned()),
+        Value::Bool(true),
+        Value::String("True".to_owned()),
+        Value::Number((-12).into()),
+        Value::String("012".to_owned()),
+        Value::String("0x1F".to_owned()),
+        Value::Number(1500.0.into()),
+        Value::String(".inf".to_owned()),
+    ];
+
+    let options = DeserializerOptions::new().schema(Schema::Json);
+    let de = Deserializer::from_str(yaml).with_options(options);
+    let value: Value = serde::Deserialize::deserialize(de).unwrap();
+    assert_eq!(Value::Sequence(expected), value);
+}
+
+#[test]
+fn test_spanned() {
+    #[derive(Deserialize, Debug)]
+    struct Config {
+        name: Spanned<String>,
+        ports: Spanned<Vec<Spanned<u16>>>,
+        limits: Spanned<BTreeMap<String, u32>>,
+        alias: Spanned<Vec<u16>>,
+    }
+
+    let yaml = indoc! {"
+        name: web
+        ports: &ports [80, 443]
+        limits:
+          cpu: 2
+        alias: *ports
+    "};
+
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+
+    fn span<T>(spanned: &Spanned<T>) -> ((usize, usize), (usize, usize)) {
+        let start = spanned.start();
+        let end = spanned.end();
+        ((start.line(), start.column()), (end.line(), end.column()))
+    }
+
+    assert_eq!(config.name.get_ref(), "web");
+    assert_eq!(span(&config.name), ((1, 7), (1, 10)));
+    assert_eq!(span(&config.ports), ((2, 8), (2, 24)));
+    assert_eq!(span(&config.ports.get_ref()[1]), ((2, 20), (2, 23)));
+    assert_eq!(*config.ports.get_ref()[1].get_ref(), 443);
+    assert_eq!(span(&config.limits), ((4, 3), (5, 1)));
+    assert_eq!(config.limits.get_ref()["cpu"], 2);
+    assert_eq!(span(&config.alias), ((5, 8), (5, 14)));
+    assert_eq!(config.alias.into_inner(), [80, 443]);
+}
+
+#[test]
+fn test_node() {
+    let yaml = indoc! {"
+        name: web
+        ports: [80, !Tcp 443]
+        env:
+          - DEBUG
+    "};
+
+    let node: Node = serde_yaml::from_str(yaml).unwrap();
+
+    fn span(node: &Node) -> ((usize, usize), (usize, usize)) {
+        let start = node.start();
+        let end = node.end();
+        ((start.line(), start.column()), (end.line(), end.column()))
+    }
+
+    assert_eq!(span(&node), ((1, 1), (5, 1)));
+    let entries = match node.value() {
+        NodeValue::Mapping(entries) => entries,
+        other => panic!("expected mapping, got {:?}", other),
+    };
+    assert_eq!(entries.len(), 3);
+    assert_eq!(span(&entries[0].0), ((1, 1), (1, 5)));
+    assert_eq!(span(&entries[0].1), ((1, 7), (1, 10)));
+    assert_eq!(span(&entries[1].1), ((2, 8), (2, 22)));
+    let ports = match entries[1].1.value() {
+        NodeValue::Sequence(ports) => ports,
+        other => panic!("expected sequence, got {:?}", other),
+    };
+    assert_eq!(span(&ports[1]), ((2, 13), (2, 21)));
+    match ports[1].value() {
+        NodeValue::Tagged(tag, value) => {
+            assert_eq!(tag, "Tcp");
+            assert_eq!(**value, NodeValue::Number(Number::from(443)));
+        }
+        other => panic!("expected tagged, got {:?}", other),
+    }
+    assert_eq!(span(&entries[2].0), ((3, 1), (3, 4)));
+    assert_eq!(span(&entries[2].1), ((4, 3), (5, 1)));
+
+    let expected: Value = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(node.clone().into_value(), expected);
+    assert_eq!(
+        serde_yaml::to_string(&node).unwrap(),
+        serde_yaml::to_string(&expected).unwrap()
+    );
+}
+
+#[test]
+fn test_ignored_keys() {
+    #[derive(Deserialize, Debug)]
+    struct Config {
+        name: String,
+        servers: Vec<Server>,
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Server {
+        host: String,
+    }
+
+    let yaml = indoc! {"
+        name: web
+        nmae: typo
+        servers:
+          - host: a
+            prot: 80
+          - host: b
+            tags: &tags [x, y]
+        env:
+          DEBUG: '1'
+        other: *tags
+    "};
+
+    let mut ignored = Vec::new();
+    let de = Deserializer::from_str(yaml);
+    let config: Config = de
+        .deserialize_with_ignored_keys(|path, location| {
+            ignored.push((path.to_owned(), location.line(), location.column()));
+        })
+        .unwrap();
+
+    assert_eq!(config.name, "web");
+    assert_eq!(config.servers[1].host, "b");
+    assert_eq!(config.env["DEBUG"], "1");
+    let expected = [
+        ("nmae".to_owned(), 2, 1),
+        ("servers[0].prot".to_owned(), 5, 5),
+        ("servers[1].tags".to_owned(), 7, 5),
+        ("other".to_owned(), 10, 1),
+    ];
+    assert_eq!(ignored, expected);
+}
+
+#[test]
+fn test_timestamp() {
+    #[derive(Deserialize, Debug)]
+    struct Times {
+        date: Timestamp,
+        canonical: Timestamp,
+        iso8601: Timestamp,
+        spaced: Timestamp,
+        no_zone: Timestamp,
+    }
+
+    let yaml = indoc! {"
+        date: 2002-12-14
+        canonical: 2001-12-15T02:59:43.1Z
+        iso8601: 2001-12-14t21:59:43.10-05:00
+        spaced: 2001-12-14 21:59:43.10 -5
+        no_zone: 2001-12-15 2:59:43.123456789123
+    "};
+    let times: Times = serde_yaml::from_str(yaml).unwrap();
+    assert!(!times.date.has_time());
+    assert_eq!(times.date.to_string(), "2002-12-14");
+    assert_eq!(times.canonical.utc_offset(), Some(0));
+    assert_eq!(times.canonical.nanosecond(), 100_000_000);
+    assert_eq!(times.canonical.to_string(), "2001-12-15T02:59:43.1Z");
+    assert_eq!(times.iso8601, times.spaced);
+    assert_eq!(times.iso8601.hour(), 21);
+    assert_eq!(times.iso8601.utc_offset(), Some(-5 * 3600));
+    assert_eq!(times.iso8601.to_string(), "2001-12-14T21:59:43.1-05:00");
+    assert_eq!(times.no_zone.utc_offset(), None);
+    assert_eq!(times.no_zone.nanosecond(), 123_456_789);
+    assert_eq!(times.no_zone.to_string(), "2001-12-15T02:59:43.123456789");
+
+    let yaml = indoc! {"
+        - 2001-12-14
+        - 2001-12-14 21:59:43.10 -5
+        - !!timestamp 2001-12-14
+        - 2001-02-29
+        - 2001-12-14 25:00:00
+    "};
+    let timestamp = |s: &str| {
+        Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new("!!timestamp"),
+            value: Value::String(s.to_owned()),
+        }))
+    };
+
+    // The YAML 1.2 schemas have no timestamp type.
+    let value: Value = serde_yaml::from_str(yaml).unwrap();
+    let expected = vec![
+        Value::String("2001-12-14".to_owned()),
+        Value::String("2001-12-14 21:59:43.10 -5".to_owned()),
+        timestamp("2001-12-14"),
+        Value::String("2001-02-29".to_owned()),
+        Value::String("2001-12-14 25:00:00".to_owned()),
+    ];
+    assert_eq!(Value::Sequence(expected), value);
+
+    let options = DeserializerOptions::new().schema(Schema::Yaml11);
+    let de = Deserializer::from_str(yaml).with_options(options.clone());
+    let value: Value = serde::Deserialize::deserialize(de).unwrap();
+    let expected = vec![
+        Value::String("2001-12-14".to_owned()),
+        Value::String("2001-12-14 21:59:43.10 -5".to_owned()),
+        timestamp("2001-12-14"),
+        Value::String("2001-02-29".to_owned()),
+        Value::String("2001-12-14 25:00:00".to_owned()),
+    ];
+    assert_eq!(Value::Sequence(expected), value);
+
+    // Plain timestamps are strings to anything that deserializes any type.
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(untagged)]
+    enum Field {
+        Number(u64),
+        Text(String),
+    }
+    let de = Deserializer::from_str("[2001-12-14, 3]").with_options(options.clone());
+    let fields: Vec<Field> = serde::Deserialize::deserialize(de).unwrap();
+    let expected = vec![Field::Text("2001-12-14".to_owned()), Field::Number(3)];
+    assert_eq!(expected, fields);
+
+    // Strings and timestamps are read from either representation.
+    let value: Value = serde_yaml::from_str(yaml).unwrap();
+    let strings: Vec<String> = serde_yaml::from_value(value.clone()).unwrap();
+    assert_eq!(strings[2], "2001-12-14");
+    let de = Deserializer::from_str(yaml).with_options(options);
+    let strings: Vec<String> = serde::Deserialize::deserialize(de).unwrap();
+    assert_eq!(strings[0], "2001-12-14");
+    let timestamps: Vec<Timestamp> =
+        serde_yaml::from_value(Value::Sequence(value.as_sequence().unwrap()[..3].to_vec()))
+            .unwrap();
+    assert_eq!(timestamps[0], timestamps[2]);
+}
+
+#[test]
+fn test_event_parser() {
+    let yaml = indoc! {"
+        base: &base !thing
+          name: 'x'
+        list: [*base, \"a\\tb\"]
+        text: |
+          line
+    "};
+    let events: Vec<_> = Parser::from_str(yaml).map(Result::unwrap).collect();
+    let scalar = |value: &str, style| {
+        Event::Scalar(event::Scalar {
+            anchor: None,
+            tag: None,
+            value: value.to_owned().into(),
+            style,
+        })
+    };
+    let expected = vec![
+        Event::StreamStart,
+        Event::DocumentStart,
+        Event::MappingStart(MappingStart {
+            anchor: None,
+            tag: None,
+            style: CollectionStyle::Block,
+        }),
+        scalar("base", ScalarStyle::Plain),
+        Event::MappingStart(MappingStart {
+            anchor: Some("base".to_owned()),
+            tag: Some("!thing".to_owned()),
+            style: CollectionStyle::Block,
+        }),
+        scalar("name", ScalarStyle::Plain),
+        scalar("x", ScalarStyle::SingleQuoted),
+        Event::MappingEnd,
+        scalar("list", ScalarStyle::Plain),
+        Event::SequenceStart(SequenceStart {
+            anchor: None,
+            tag: None,
+            style: CollectionStyle::Flow,
+        }),
+        Event::Alias("base".to_owned()),
+        scalar("a\tb", ScalarStyle::DoubleQuoted),
+        Event::SequenceEnd,
+        scalar("text", ScalarStyle::Plain),
+        scalar("line\n", ScalarStyle::Literal),
+        Event::MappingEnd,
+        Event::DocumentEnd,
+        Event::StreamEnd,
+    ];
+    let actual: Vec<_> = events.iter().map(Spanned::get_ref).cloned().collect();
+    assert_eq!(expected, actual);
+
+    let start = events[6].start();
+    assert_eq!((start.line(), start.column()), (2, 9));
+    let end = events[6].end();
+    assert_eq!((end.line(), end.column()), (2, 12));
+    if let Event::Scalar(scalar) = events[6].get_ref() {
+        assert!(matches!(scalar.value, Cow::Borrowed("x")));
+    }
+
+    let mut parser = Parser::from_reader(yaml.as_bytes());
+    assert!(parser.all(|event| event.is_ok()));
+
+    let mut parser = Parser::from_str("[a, b");
+    assert!(matches!(parser.next(), Some(Ok(_))));
+    let error = parser.find_map(Result::err).unwrap();
+    assert_eq!(
+        error.to_string(),
+        "did not find expected ',' or ']' at line 2 column 1, while parsing a flow sequence",
+    );
+    assert!(parser.next().is_none());
+}
+
+#[test]
+fn test_preserve_anchors() {
+    let yaml = indoc! {"
+        base: &base !thing
+          name: &name x
+        copy: *base
+        list: [*name, &empty []]
+    "};
+    let tagged = |tag: &str, value| {
+        Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(tag),
+            value,
+        }))
+    };
+    let mut base = Mapping::new();
+    base.insert("name".into(), tagged("&name", "x".into()));
+    let mut expected = Mapping::new();
+    expected.insert(
+        "base".into(),
+        tagged("&base", tagged("!thing", Value::Mapping(base))),
+    );
+    expected.insert("copy".into(), tagged("*base", Value::Null));
+    expected.insert(
+        "list".into(),
+        Value::Sequence(vec![
+            tagged("*name", Value::Null),
+            tagged("&empty", Value::Sequence(Vec::new())),
+        ]),
+    );
+
+    let options = DeserializerOptions::new().preserve_anchors(true);
+    let de = Deserializer::from_str(yaml).with_options(options.clone());
+    let value: Value = serde::Deserialize::deserialize(de).unwrap();
+    assert_eq!(Value::Mapping(expected), value);
+
+    // Types that do not deserialize through deserialize_any still see the
+    // aliases expanded.
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Data {
+        copy: BTreeMap<String, String>,
+        list: (String, Vec<u8>),
+    }
+    let de = Deserializer::from_str(yaml).with_options(options);
+    let data: Data = serde::Deserialize::deserialize(de).unwrap();
+    assert_eq!(data.copy["name"], "x");
+    assert_eq!(data.list, ("x".to_owned(), Vec::new()));
+}
+
+#[test]
+fn test_local_tags_resembling_anchors() {
+    let tagged = |tag: &str, value| {
+        Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(tag),
+            value,
+        }))
+    };
+    let mut inner = Mapping::new();
+    inner.insert("a".into(), 1.into());
+    let cases = [
+        ("!&x 1", tagged("!&x", 1.into())),
+        ("k: !*foo bar", {
+            let mut mapping = Mapping::new();
+            mapping.insert("k".into(), tagged("!*foo", "bar".into()));
+            Value::Mapping(mapping)
+        }),
+        ("k: !&x {a: 1}", {
+            let mut mapping = Mapping::new();
+            mapping.insert("k".into(), tagged("!&x", Value::Mapping(inner)));
+            Value::Mapping(mapping)
+        }),
+    ];
+    for (yaml, expected) in &cases {
+        for preserve_anchors in [false, true] {
+            let options = DeserializerOptions::new().preserve_anchors(preserve_anchors);
+            let de = Deserializer::from_str(yaml).with_options(options);
+            let value: Value = serde::Deserialize::deserialize(de).unwrap();
+            assert_eq!(*expected, value);
+        }
+    }
+}
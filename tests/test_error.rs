@@ -6,8 +6,9 @@ use serde::de::Deserialize;
 use serde::de::{SeqAccess, Visitor};
 use serde_derive::{Deserialize, Serialize};
 use serde_yaml::value::{Tag, TaggedValue};
-use serde_yaml::{Deserializer, Value};
-#[cfg(not(miri))]
+use serde_yaml::{
+    Deserializer, DeserializerOptions, DuplicateKeyPolicy, ErrorKind, PathSegment, Value,
+};
 use std::collections::BTreeMap;
 #[cfg(not(miri))]
 use std::fmt;
@@ -120,9 +121,33 @@ fn test_ignored_unknown_anchor() {
 }
 
 #[test]
-fn test_bytes() {
-    let expected = "serialization and deserialization of bytes in YAML is not implemented";
-    test_error::<&[u8]>("...", expected);
+fn test_binary() {
+    let yaml = "data: !!binary aGVsbG8*";
+    let expected = "data: invalid value: string \"aGVsbG8*\", expected base64-encoded binary data at line 1 column 7";
+    test_error::<BTreeMap<String, Vec<u8>>>(yaml, expected);
+
+    let yaml = "data: !!binary aGVsbG8=";
+    let expected =
+        "data: invalid type: byte array, expected a borrowed byte array at line 1 column 7";
+    test_error::<BTreeMap<&str, &[u8]>>(yaml, expected);
+}
+
+#[test]
+fn test_timestamp() {
+    let yaml = "date: !!timestamp 2001-02-29";
+    let expected =
+        "date: invalid value: string \"2001-02-29\", expected a YAML timestamp at line 1 column 7";
+    test_error::<Value>(yaml, expected);
+
+    let yaml = "date: 2001-12-14 21:59";
+    let expected = "date: invalid value: string \"2001-12-14 21:59\", expected a YAML timestamp at line 1 column 7";
+    test_error::<BTreeMap<String, serde_yaml::Timestamp>>(yaml, expected);
+
+    let error = "2001-12-14 21:59"
+        .parse::<serde_yaml::Timestamp>()
+        .unwrap_err();
+    assert_eq!(error.to_string(), "failed to parse YAML timestamp");
+    assert_eq!(error.kind(), ErrorKind::Data);
 }
 
 #[test]
@@ -460,7 +485,7 @@ fn test_billion_laughs() {
         h: &h [*g,*g,*g,*g,*g,*g,*g,*g,*g]
         i: &i [*h,*h,*h,*h,*h,*h,*h,*h,*h]
     "};
-    let expected = "repetition limit exceeded";
+    let expected = "repetition limit exceeded at line 2 column 32";
     test_error::<BTreeMap<String, X>>(yaml, expected);
 }
 
@@ -498,3 +523,466 @@ fn test_duplicate_keys() {
     let expected = "duplicate entry in YAML map at line 2 column 1";
     test_error::<Value>(yaml, expected);
 }
+
+#[test]
+fn test_reader_error() {
+    struct Reader(&'static [u8]);
+
+    impl std::io::Read for Reader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(std::io::Error::new(std::io::ErrorKind::Other, "oh no"));
+            }
+            let n = buf.len().min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    let mut de = Deserializer::from_reader(Reader(b"---\n0\n---\n1\n"));
+    let first_doc = de.next().unwrap();
+    assert_eq!(0, usize::deserialize(first_doc).unwrap());
+
+    let second_doc = de.next().unwrap();
+    let error = usize::deserialize(second_doc).unwrap_err();
+    assert_eq!("oh no", error.to_string());
+    assert_eq!(ErrorKind::Io, error.kind());
+    assert!(de.next().is_none());
+}
+
+#[test]
+fn test_deserializer_options() {
+    fn test_options(yaml: &str, options: DeserializerOptions, expected: &str) {
+        let de = Deserializer::from_str(yaml).with_options(options.clone());
+        let result = Value::deserialize(de);
+        assert_eq!(expected, result.unwrap_err().to_string());
+
+        let mut de = Deserializer::from_str(yaml).with_options(options);
+        let document = de.next().unwrap();
+        let result = Value::deserialize(document);
+        assert_eq!(expected, result.unwrap_err().to_string());
+    }
+
+    let yaml = indoc! {"
+        a: [[[0]]]
+    "};
+    let options = DeserializerOptions::new().max_depth(3);
+    let expected = "recursion limit exceeded at line 1 column 6";
+    test_options(yaml, options, expected);
+
+    let yaml = indoc! {"
+        a: &a [0, 0]
+        b: [*a, *a, *a]
+    "};
+    let options = DeserializerOptions::new().max_alias_expansions(2);
+    let expected = "repetition limit exceeded at line 2 column 13";
+    test_options(yaml, options, expected);
+
+    let yaml = indoc! {"
+        a: [0, 1, 2, 3]
+    "};
+    let options = DeserializerOptions::new().max_events(6);
+    let expected = "event limit exceeded at line 1 column 14";
+    test_options(yaml, options, expected);
+
+    let yaml = indoc! {"
+        a: short
+        b: long enough
+    "};
+    let options = DeserializerOptions::new().max_scalar_length(8);
+    let expected = "scalar length limit exceeded at line 2 column 4";
+    test_options(yaml, options, expected);
+
+    let yaml = indoc! {"
+        a: 0
+        b: 1
+        c: 2
+    "};
+    let options = DeserializerOptions::new().max_document_bytes(12);
+    let expected = "document size limit exceeded at line 3 column 4";
+    test_options(yaml, options, expected);
+
+    let yaml = indoc! {"
+        a: 0
+        b: a single scalar running past the limit
+    "};
+    let options = DeserializerOptions::new().max_document_bytes(12);
+    let expected = "document size limit exceeded at line 2 column 4";
+    test_options(yaml, options, expected);
+}
+
+#[test]
+fn test_duplicate_key_policy() {
+    #[derive(Deserialize, Debug)]
+    pub struct Outer {
+        #[allow(dead_code)]
+        pub inner: BTreeMap<String, usize>,
+    }
+
+    let yaml = indoc! {"
+        inner:
+          a: 1
+          b: 2
+          a: 3
+    "};
+    let options = DeserializerOptions::new().duplicate_keys(DuplicateKeyPolicy::Error);
+    let de = Deserializer::from_str(yaml).with_options(options);
+    let result = Outer::deserialize(de);
+    let expected = "inner: duplicate entry with key \"a\" (first defined at line 2 column 3) at line 4 column 3";
+    assert_eq!(expected, result.unwrap_err().to_string());
+}
+
+#[test]
+fn test_merge_keys() {
+    fn test_merge_error(yaml: &str, expected: &str) {
+        let options = DeserializerOptions::new().merge_keys(true);
+        let de = Deserializer::from_str(yaml).with_options(options);
+        let result = BTreeMap::<String, Value>::deserialize(de);
+        assert_eq!(expected, result.unwrap_err().to_string());
+    }
+
+    let yaml = indoc! {"
+        a: &a 1
+        b:
+          <<: *a
+    "};
+    let expected = "expected a mapping or list of mappings for merging, but found scalar";
+    test_merge_error(yaml, expected);
+
+    let yaml = indoc! {"
+        a: &a [{x: 1}]
+        b:
+          <<: [*a]
+    "};
+    let expected = "expected a mapping for merging, but found sequence";
+    test_merge_error(yaml, expected);
+
+    let yaml = indoc! {"
+        b:
+          <<: [{x: 1}, 2]
+    "};
+    let expected = "expected a mapping for merging, but found scalar";
+    test_merge_error(yaml, expected);
+
+    let yaml = indoc! {"
+        b:
+          <<: !tag {x: 1}
+    "};
+    let expected = "unexpected tagged value in merge";
+    test_merge_error(yaml, expected);
+
+    let yaml = indoc! {"
+        a: &a
+          <<: *a
+    "};
+    let expected = "recursion limit exceeded at line 1 column 4";
+    test_merge_error(yaml, expected);
+}
+
+#[test]
+fn test_collect_errors() {
+    #[derive(Deserialize, Debug)]
+    #[serde(deny_unknown_fields)]
+    struct Config {
+        name: String,
+        #[allow(dead_code)]
+        servers: Vec<Server>,
+        #[allow(dead_code)]
+        limits: Option<Limits>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Server {
+        #[allow(dead_code)]
+        host: String,
+        #[allow(dead_code)]
+        port: u16,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Limits {
+        #[allow(dead_code)]
+        cpu: u32,
+    }
+
+    fn collect_errors(yaml: &str, options: DeserializerOptions) -> Vec<String> {
+        let de = Deserializer::from_str(yaml).with_options(options);
+        let errors = de.collect_errors::<Config>().unwrap_err();
+        errors.iter().map(ToString::to_string).collect()
+    }
+
+    let yaml = indoc! {"
+        name: [web]
+        servers:
+          - host: a
+            port: 80
+          - host: b
+            port: -1
+          - host: c
+          - &bad {host: d, port: x}
+          - *bad
+        limits:
+          cpu: many
+        extra: 1
+    "};
+    let expected = [
+        "name: invalid type: sequence, expected a string at line 1 column 7",
+        "servers[1].port: invalid type: integer `-1`, expected u16 at line 6 column 11",
+        "servers[2]: missing field `port` at line 7 column 5",
+        "servers[3].port: invalid type: string \"x\", expected u16 at line 8 column 26",
+        "limits.cpu: invalid type: string \"many\", expected u32 at line 11 column 8",
+        "unknown field `extra`, expected one of `name`, `servers`, `limits` at line 12 column 1",
+    ];
+    assert_eq!(
+        expected[..],
+        collect_errors(yaml, DeserializerOptions::new())
+    );
+
+    let options = DeserializerOptions::new().max_errors(2);
+    assert_eq!(expected[..2], collect_errors(yaml, options));
+
+    // Every pass expands the aliases again, each within a budget of its own.
+    let yaml = indoc! {"
+        name: [web]
+        servers:
+          - &server {host: a, port: 80}
+          - *server
+        limits:
+          cpu: many
+    "};
+    let expected = [
+        "name: invalid type: sequence, expected a string at line 1 column 7",
+        "limits.cpu: invalid type: string \"many\", expected u32 at line 6 column 8",
+    ];
+    let options = DeserializerOptions::new().max_alias_expansions(2);
+    assert_eq!(expected[..], collect_errors(yaml, options));
+
+    let yaml = "name: web\nservers: {}\nlimits: [\n";
+    let expected =
+        ["did not find expected node content at line 4 column 1, while parsing a flow node"];
+    assert_eq!(
+        expected[..],
+        collect_errors(yaml, DeserializerOptions::new())
+    );
+
+    let yaml = "name: web\nservers: []\n";
+    let de = Deserializer::from_str(yaml);
+    let config = de.collect_errors::<Config>().unwrap();
+    assert_eq!(config.name, "web");
+}
+
+#[test]
+fn test_error_kind() {
+    let error = serde_yaml::from_str::<Value>("key: [").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Syntax);
+
+    let error = serde_yaml::from_str::<Value>("*unknown").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnknownAnchor);
+
+    let error = serde_yaml::from_str::<Value>("---\na\n---\nb\n").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::MoreThanOneDocument);
+
+    let options = DeserializerOptions::new().duplicate_keys(DuplicateKeyPolicy::Error);
+    let de = Deserializer::from_str("a: 1\na: 2\n").with_options(options);
+    let error = BTreeMap::<String, u8>::deserialize(de).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::DuplicateKey);
+
+    let error = serde_yaml::from_str::<(u8, u8)>("[1]").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Data);
+    assert!(!error.kind().is_limit_exceeded());
+
+    let options = DeserializerOptions::new().max_scalar_length(3);
+    let de = Deserializer::from_str("long").with_options(options);
+    let error = Value::deserialize(de).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::ScalarLengthLimitExceeded);
+    assert!(error.kind().is_limit_exceeded());
+
+    // Errors shared between the documents of a stream keep their kind.
+    let mut de = Deserializer::from_str("a: [\n");
+    let error = Value::deserialize(de.next().unwrap()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Syntax);
+
+    #[derive(Serialize, Debug)]
+    enum Outer {
+        Inner(Inner),
+    }
+    #[derive(Serialize, Debug)]
+    enum Inner {
+        Newtype(usize),
+    }
+    let error = serde_yaml::to_string(&Outer::Inner(Inner::Newtype(0))).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+}
+
+#[test]
+fn test_error_path() {
+    #[derive(Deserialize, Debug)]
+    pub struct Config {
+        #[allow(dead_code)]
+        pub defaults: Limits,
+        #[allow(dead_code)]
+        pub jobs: Vec<BTreeMap<String, Limits>>,
+    }
+    #[derive(Deserialize, Debug)]
+    pub struct Limits {
+        #[allow(dead_code)]
+        pub memory: u32,
+    }
+    let yaml = indoc! {"
+        defaults: &defaults
+          memory: lots
+        jobs:
+        - build: *defaults
+    "};
+    let error = serde_yaml::from_str::<Config>(yaml).unwrap_err();
+    assert_eq!(
+        "defaults.memory: invalid type: string \"lots\", expected u32 at line 2 column 11",
+        error.to_string(),
+    );
+    let expected = [
+        PathSegment::Key("defaults".to_owned()),
+        PathSegment::Key("memory".to_owned()),
+    ];
+    assert_eq!(error.path().unwrap(), expected);
+
+    let yaml = indoc! {"
+        defaults: &defaults
+          memory: 1
+        jobs:
+        - build: *defaults
+          test: {memory: -1}
+    "};
+    let error = serde_yaml::from_str::<Config>(yaml).unwrap_err();
+    let expected = [
+        PathSegment::Key("jobs".to_owned()),
+        PathSegment::Index(0),
+        PathSegment::Key("test".to_owned()),
+        PathSegment::Key("memory".to_owned()),
+    ];
+    assert_eq!(error.path().unwrap(), expected);
+
+    let yaml = "defaults: &defaults {memory: 1}\njobs: *defaults\n";
+    let error = serde_yaml::from_str::<Config>(yaml).unwrap_err();
+    let expected = [PathSegment::Key("jobs".to_owned()), PathSegment::Alias];
+    assert_eq!(error.path().unwrap(), expected);
+
+    let error = serde_yaml::from_str::<BTreeMap<u8, u8>>("1: x\n").unwrap_err();
+    assert_eq!(error.path().unwrap(), [PathSegment::Key("1".to_owned())]);
+
+    let error = serde_yaml::from_str::<BTreeMap<Vec<u8>, u8>>("[1]: x\n").unwrap_err();
+    assert_eq!(error.path().unwrap(), [PathSegment::Unknown]);
+
+    let error = serde_yaml::from_str::<u8>("x").unwrap_err();
+    assert_eq!(error.path().unwrap(), []);
+
+    let error = serde_yaml::from_str::<Value>("[").unwrap_err();
+    assert_eq!(error.path(), None);
+}
+
+#[test]
+fn test_error_span() {
+    fn span(yaml: &str, error: &serde_yaml::Error) -> String {
+        let start = error.location().unwrap().index();
+        let end = error.end_location().unwrap().index();
+        yaml[start..end].to_owned()
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct Job {
+        #[allow(dead_code)]
+        pub image: String,
+        #[allow(dead_code)]
+        pub steps: Vec<String>,
+    }
+
+    let yaml = indoc! {"
+        image: rust
+        steps: {build: cargo build}
+    "};
+    let error = serde_yaml::from_str::<Job>(yaml).unwrap_err();
+    assert_eq!(span(yaml, &error), "{build: cargo build}");
+
+    let yaml = indoc! {"
+        image:
+          name: rust
+          tag: latest
+        steps: []
+    "};
+    let error = serde_yaml::from_str::<Job>(yaml).unwrap_err();
+    assert_eq!(span(yaml, &error), "name: rust\n  tag: latest\n");
+
+    let options = DeserializerOptions::new().duplicate_keys(DuplicateKeyPolicy::Error);
+    let yaml = "image: rust\n'image': debian\n";
+    let de = Deserializer::from_str(yaml).with_options(options);
+    let error = BTreeMap::<String, String>::deserialize(de).unwrap_err();
+    assert_eq!(span(yaml, &error), "'image'");
+
+    let yaml = "image: [rust\nsteps: []\n";
+    let error = serde_yaml::from_str::<Value>(yaml).unwrap_err();
+    assert_eq!(error.end_location(), None);
+    let location = error.location().unwrap();
+    assert_eq!((location.line(), location.column()), (2, 6));
+    let context = error.context_location().unwrap();
+    assert_eq!((context.line(), context.column()), (1, 8));
+}
+
+#[test]
+fn test_render() {
+    #[derive(Deserialize, Debug)]
+    pub struct Limits {
+        #[allow(dead_code)]
+        pub memory: u32,
+    }
+    let yaml = indoc! {"
+        # limits for every job
+        memory: 4 GiB
+    "};
+    let error = serde_yaml::from_str::<Limits>(yaml).unwrap_err();
+    let expected = indoc! {"
+        error: memory: invalid type: string \"4 GiB\", expected u32
+         --> limits.yaml:2:9
+          |
+        2 | memory: 4 GiB
+          |         ^^^^^
+    "};
+    assert_eq!(expected, error.render(yaml, "limits.yaml").to_string());
+
+    let expected = concat!(
+        "\x1b[1;31merror\x1b[0m\x1b[1m: memory: invalid type: string \"4 GiB\", expected u32\x1b[0m\n",
+        "\x1b[1;34m --> \x1b[0mlimits.yaml:2:9\n",
+        "\x1b[1;34m  |\x1b[0m\n",
+        "\x1b[1;34m2 |\x1b[0m memory: 4 GiB\n",
+        "\x1b[1;34m  |\x1b[0m         \x1b[1;31m^^^^^\x1b[0m\n",
+    );
+    let rendered = error.render(yaml, "limits.yaml").color(true).to_string();
+    assert_eq!(expected, rendered);
+
+    let yaml = indoc! {"
+        jobs:
+          build: {
+            image: rust,
+
+            steps: [cargo build]
+          test: {}
+    "};
+    let error = serde_yaml::from_str::<Value>(yaml).unwrap_err();
+    let expected = indoc! {"
+        error: did not find expected ',' or '}'
+         --> jobs.yaml:6:3
+          |
+        2 |   build: {
+          |          - while parsing a flow mapping
+        ...
+        6 |   test: {}
+          |   ^
+    "};
+    assert_eq!(expected, error.render(yaml, "jobs.yaml").to_string());
+
+    let error = serde_yaml::from_str::<String>("").unwrap_err();
+    let expected = indoc! {"
+        error: EOF while parsing a value
+         --> empty.yaml
+    "};
+    assert_eq!(expected, error.render("", "empty.yaml").to_string());
+}
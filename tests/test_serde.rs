@@ -6,9 +6,15 @@
 )]
 
 use indoc::indoc;
-use serde::ser::SerializeMap;
+use serde::de::Deserialize;
+use serde::ser::{Serialize as _, SerializeMap};
 use serde_derive::{Deserialize, Serialize};
-use serde_yaml::{Mapping, Number, Value};
+use serde_yaml::event::{Emitter, Event, Parser};
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::{
+    Deserializer, DeserializerOptions, Document, Mapping, Number, QuotePolicy, Serializer,
+    SerializerOptions, Timestamp, Value,
+};
 use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::iter;
@@ -37,6 +43,17 @@ where
     serde_yaml::from_str::<serde::de::IgnoredAny>(yaml).unwrap();
 }
 
+fn to_string_with_options<T>(documents: &[T], options: SerializerOptions) -> String
+where
+    T: serde::Serialize,
+{
+    let mut ser = Serializer::new(Vec::new()).with_options(options);
+    for document in documents {
+        document.serialize(&mut ser).unwrap();
+    }
+    String::from_utf8(ser.into_inner().unwrap()).unwrap()
+}
+
 #[test]
 fn test_default() {
     assert_eq!(Value::default(), Value::Null);
@@ -292,6 +309,68 @@ fn test_multiline_string() {
     test_serde(&thing, yaml);
 }
 
+#[test]
+fn test_binary() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Data {
+        #[serde(with = "serde_yaml::with::binary")]
+        empty: Vec<u8>,
+        #[serde(with = "serde_yaml::with::binary")]
+        short: Vec<u8>,
+        #[serde(with = "serde_yaml::with::binary")]
+        long: Vec<u8>,
+    }
+    let thing = Data {
+        empty: Vec::new(),
+        short: b"hello".to_vec(),
+        long: (0..100).collect(),
+    };
+    let yaml = indoc! {"
+        empty: !!binary ''
+        short: !!binary aGVsbG8=
+        long: !!binary |
+          AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8gISIjJCUmJygpKissLS4vMDEyMzQ1Njc4
+          OTo7PD0+P0BBQkNERUZHSElKS0xNTk9QUVJTVFVWV1hZWltcXV5fYGFiYw==
+    "};
+    test_serde(&thing, yaml);
+
+    // Base64 may be wrapped at any width, and Vec<u8> without `with` reads it
+    // as a sequence.
+    let yaml = indoc! {"
+        - !!binary |
+          aGVs
+          bG8=
+        - !!binary \"aGVs\\nbG8\"
+        - [104, 101, 108, 108, 111]
+    "};
+    let deserialized: Vec<Vec<u8>> = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(deserialized, [b"hello"; 3]);
+    let value: Value = serde_yaml::from_str(yaml).unwrap();
+    let deserialized: Vec<Vec<u8>> = serde_yaml::from_value(value).unwrap();
+    assert_eq!(deserialized, [b"hello"; 3]);
+}
+
+#[test]
+fn test_timestamp() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Release {
+        date: Timestamp,
+        published: Timestamp,
+        name: String,
+    }
+    let thing = Release {
+        date: "2001-12-14".parse().unwrap(),
+        published: "2001-12-14 21:59:43.10 -5".parse().unwrap(),
+        name: "2002-12-14".to_owned(),
+    };
+    let yaml = indoc! {"
+        date: 2001-12-14
+        published: 2001-12-14T21:59:43.1-05:00
+        name: 2002-12-14
+    "};
+    test_serde(&thing, yaml);
+}
+
 #[test]
 fn test_strings_needing_quote() {
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -576,3 +655,637 @@ fn test_long_string() {
 
     test_serde(&thing, yaml);
 }
+
+#[test]
+fn test_event_emitter() {
+    // Parsing and re-emitting preserves anchors, aliases, tags and styles.
+    let yaml = indoc! {"
+        base: &base !thing
+          name: 'x'
+          id: !!str 1
+        list: [*base, \"a\\tb\", {k: v}]
+        text: |
+          line
+        folded: >
+          word
+    "};
+    let mut emitter = Emitter::new(Vec::new());
+    for event in Parser::from_str(yaml) {
+        emitter.emit(event.unwrap().into_inner()).unwrap();
+    }
+    let output = emitter.into_inner().unwrap();
+    assert_eq!(yaml, String::from_utf8(output).unwrap());
+
+    let mut emitter = Emitter::new(Vec::new());
+    emitter.emit(Event::StreamStart).unwrap();
+    emitter.emit(Event::DocumentStart).unwrap();
+    let error = emitter.emit(Event::MappingEnd).unwrap_err();
+    assert_eq!(
+        "expected SCALAR, SEQUENCE-START, MAPPING-START, or ALIAS",
+        error.to_string(),
+    );
+}
+
+#[test]
+fn test_preserve_anchors() {
+    let yaml = indoc! {"
+        defaults: &defaults !config
+          image: &image rust
+          retries: 3
+        jobs:
+        - *defaults
+        - image: *image
+          steps: &steps
+          - build
+        - *steps
+    "};
+    let options = DeserializerOptions::new().preserve_anchors(true);
+    let de = Deserializer::from_str(yaml).with_options(options);
+    let value = Value::deserialize(de).unwrap();
+    let options = SerializerOptions::new().preserve_anchors(true);
+    assert_eq!(yaml, to_string_with_options(&[&value], options.clone()));
+
+    let value = serde_yaml::to_value(&value).unwrap();
+    assert_eq!(yaml, to_string_with_options(&[&value], options.clone()));
+
+    // Without the option, the tags are written as local tags.
+    let tagged = |tag: &str, value| {
+        Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(tag),
+            value,
+        }))
+    };
+    let mut mapping = Mapping::new();
+    mapping.insert("a".into(), tagged("&x", 1.into()));
+    mapping.insert("b".into(), tagged("*x", Value::Null));
+    let value = Value::Mapping(mapping);
+    let yaml = serde_yaml::to_string(&value).unwrap();
+    assert_eq!(yaml, "a: !&x 1\nb: !*x null\n");
+    assert_eq!(serde_yaml::from_str::<Value>(&yaml).unwrap(), value);
+
+    // An alias cannot carry a value of its own.
+    let alias = tagged("*x", 1.into());
+    let mut ser = Serializer::new(Vec::new()).with_options(options);
+    let error = alias.serialize(&mut ser).unwrap_err();
+    assert_eq!(error.to_string(), "a YAML alias cannot have a value");
+}
+
+#[test]
+fn test_document() {
+    let yaml = indoc! {"
+        # Service configuration
+        name: app  # shown in the dashboard
+        replicas:
+        labels: {tier: web, team: infra}
+        ports:
+          - 80  # http
+          - 443
+        jobs:
+        - name: build
+          run: cargo build
+        - name: test
+          run: |
+            cargo test
+    "};
+    let mut doc: Document = yaml.parse().unwrap();
+    assert_eq!(
+        doc.get("jobs.1.name"),
+        Some(Value::String("test".to_owned()))
+    );
+    assert_eq!(doc.get("jobs.2"), None);
+
+    fn edit(doc: &mut Document) {
+        doc.set("name", "server").unwrap();
+        doc.set("replicas", 3).unwrap();
+        doc.set("labels.tier", "true").unwrap();
+        doc.insert("labels.owner", vec!["ops", "a, b"]).unwrap();
+        doc.insert("ports.1", 8080).unwrap();
+        doc.set("jobs.1.run", "cargo test\ncargo doc\n").unwrap();
+        doc.insert("jobs.0.env", BTreeMap::from([("CI", "1")]))
+            .unwrap();
+        doc.remove("jobs.1.name").unwrap();
+    }
+    edit(&mut doc);
+    let expected = indoc! {"
+        # Service configuration
+        name: server  # shown in the dashboard
+        replicas: 3
+        labels: {tier: 'true', team: infra, owner: [ops, 'a, b']}
+        ports:
+          - 80  # http
+          - 8080
+          - 443
+        jobs:
+        - name: build
+          run: cargo build
+          env:
+            CI: '1'
+        - run: |
+            cargo test
+            cargo doc
+    "};
+    assert_eq!(expected, doc.as_str());
+
+    // Inserted lines get the same line breaks as the rest of the document.
+    let mut crlf_doc: Document = yaml.replace('\n', "\r\n").parse().unwrap();
+    edit(&mut crlf_doc);
+    assert_eq!(expected.replace('\n', "\r\n"), crlf_doc.as_str());
+
+    assert_eq!(doc.remove("ports.0").unwrap(), Value::Number(80.into()));
+    doc.remove("ports.0").unwrap();
+    doc.remove("ports.0").unwrap();
+    doc.remove("jobs").unwrap();
+    let expected = indoc! {"
+        # Service configuration
+        name: server  # shown in the dashboard
+        replicas: 3
+        labels: {tier: 'true', team: infra, owner: [ops, 'a, b']}
+        ports: []
+    "};
+    assert_eq!(expected, doc.to_string());
+}
+
+#[test]
+fn test_document_errors() {
+    let yaml = indoc! {"
+        base: &base
+          image: rust
+        job: *base
+    "};
+    let mut doc: Document = yaml.parse().unwrap();
+    let error = doc.set("version", 1).unwrap_err();
+    assert_eq!("no node at `version`", error.to_string());
+    let error = doc.set("job.image", "debian").unwrap_err();
+    assert_eq!("path `job.image` goes through an alias", error.to_string());
+    let error = doc.set("base", "none").unwrap_err();
+    assert_eq!(
+        "editing `base` would change other parts of the document",
+        error.to_string(),
+    );
+    assert_eq!(yaml, doc.as_str());
+
+    let error = "a: 1\n---\nb: 2\n".parse::<Document>().unwrap_err();
+    assert_eq!(
+        "a Document cannot hold more than one YAML document",
+        error.to_string(),
+    );
+}
+
+#[test]
+fn test_patch() {
+    let yaml = indoc! {"
+        package:
+            name: demo     # aligned
+            version: 0.1.0 # aligned
+        targets: [linux, macos]
+    "};
+    let patched = serde_yaml::document::patch(yaml, "package.version", "0.2.0").unwrap();
+    let expected = indoc! {"
+        package:
+            name: demo     # aligned
+            version: 0.2.0 # aligned
+        targets: [linux, macos]
+    "};
+    assert_eq!(expected, patched);
+
+    let targets = BTreeMap::from([("linux", vec!["x86_64", "aarch64"])]);
+    let patched = serde_yaml::document::patch(yaml, "targets", targets).unwrap();
+    let expected = indoc! {"
+        package:
+            name: demo     # aligned
+            version: 0.1.0 # aligned
+        targets:
+            linux:
+            - x86_64
+            - aarch64
+    "};
+    assert_eq!(expected, patched);
+
+    let patched = serde_yaml::document::patch(yaml, "targets.1", "windows").unwrap();
+    assert!(patched.ends_with("targets: [linux, windows]\n"));
+
+    let error = serde_yaml::document::patch(yaml, "package.edition", 2021).unwrap_err();
+    assert_eq!("no node at `package.edition`", error.to_string());
+}
+
+#[test]
+fn test_serializer_options() {
+    let yaml = indoc! {"
+        name: web
+        ports:
+        - 80
+        - 443
+        volumes:
+        - source: data
+          targets:
+          - /var/lib
+          - /srv
+          script: |
+            make
+            make install
+        - - a
+          - b
+        env: {}
+    "};
+    let value: Value = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        to_string_with_options(&[&value], SerializerOptions::new()),
+        yaml
+    );
+
+    let options = SerializerOptions::new().indent(4).indent_sequences(true);
+    let expected = indoc! {"
+        name: web
+        ports:
+            - 80
+            - 443
+        volumes:
+            -   source: data
+                targets:
+                    - /var/lib
+                    - /srv
+                script: |
+                    make
+                    make install
+            -   - a
+                - b
+        env: {}
+    "};
+    let serialized = to_string_with_options(&[&value], options);
+    assert_eq!(serialized, expected);
+    assert_eq!(serde_yaml::from_str::<Value>(&serialized).unwrap(), value);
+
+    let options = SerializerOptions::new().mappings_on_dash_line(false);
+    let expected = indoc! {"
+        name: web
+        ports:
+        - 80
+        - 443
+        volumes:
+        -
+          source: data
+          targets:
+          - /var/lib
+          - /srv
+          script: |
+            make
+            make install
+        - - a
+          - b
+        env: {}
+    "};
+    let serialized = to_string_with_options(&[&value], options);
+    assert_eq!(serialized, expected);
+    assert_eq!(serde_yaml::from_str::<Value>(&serialized).unwrap(), value);
+
+    let options = SerializerOptions::new().indent_sequences(true);
+    let documents = [
+        BTreeMap::from([("ports", vec![80])]),
+        BTreeMap::from([("ports", vec![443, 8443])]),
+    ];
+    let expected = indoc! {"
+        ports:
+          - 80
+        ---
+        ports:
+          - 443
+          - 8443
+    "};
+    assert_eq!(to_string_with_options(&documents, options), expected);
+
+    let yaml = indoc! {"
+        ? - a
+          - b
+        : - c
+          - d
+    "};
+    let value: Value = serde_yaml::from_str(yaml).unwrap();
+    let options = SerializerOptions::new().indent_sequences(true);
+    assert_eq!(to_string_with_options(&[&value], options), yaml);
+}
+
+#[test]
+fn test_serializer_width() {
+    let options = SerializerOptions::new().width(20);
+    let strings = [
+        "short",
+        "this string is too long to fit on one line",
+        "  leading spaces and   runs of   spaces in a long string",
+        "trailing space in a string that needs folding ",
+        "key: value # not a comment, and long enough to be wrapped",
+        "'quotes' and \"double quotes\" and a \ttab and more words",
+        "averyveryverylongwordthatcannotbebrokenanywhere at all",
+        "true false null 12 0x1F and other things that look like other types",
+    ];
+    let mut map = BTreeMap::new();
+    map.insert("list", strings.to_vec());
+
+    let yaml = to_string_with_options(&[&map], options);
+    let expected = indoc! {r#"
+        list:
+        - short
+        - >-
+          this string is too long
+          to fit on one line
+        - '  leading spaces and   runs
+          of   spaces in a long
+          string'
+        - "trailing space in a
+          string that needs folding "
+        - >-
+          key: value # not a comment,
+          and long enough to be
+          wrapped
+        - "'quotes' and \"double
+          quotes\" and a \ttab
+          and more words"
+        - >-
+          averyveryverylongwordthatcannotbebrokenanywhere
+          at all
+        - >-
+          true false null 12 0x1F
+          and other things that
+          look like other types
+    "#};
+    let deserialized: BTreeMap<String, Vec<String>> = serde_yaml::from_str(&yaml).unwrap();
+    assert_eq!(deserialized["list"], strings);
+    assert_eq!(yaml, expected);
+}
+
+#[test]
+fn test_quote_policy() {
+    #[derive(Serialize)]
+    struct Config {
+        name: &'static str,
+        enabled: &'static str,
+        mode: &'static str,
+        separator: char,
+        address: std::net::Ipv4Addr,
+        env: BTreeMap<&'static str, &'static str>,
+        tagged: Value,
+    }
+
+    let config = Config {
+        name: "web",
+        enabled: "yes",
+        mode: "0777",
+        separator: ',',
+        address: std::net::Ipv4Addr::LOCALHOST,
+        env: BTreeMap::from([("true", "1.0")]),
+        tagged: serde_yaml::from_str("!Port 80").unwrap(),
+    };
+
+    let expected = indoc! {"
+        name: web
+        enabled: yes
+        mode: '0777'
+        separator: ','
+        address: 127.0.0.1
+        env:
+          'true': '1.0'
+        tagged: !Port 80
+    "};
+    assert_eq!(
+        to_string_with_options(&[&config], SerializerOptions::new()),
+        expected
+    );
+
+    let options = SerializerOptions::new().quote_values(QuotePolicy::WhenAmbiguous);
+    let expected = indoc! {"
+        name: web
+        enabled: 'yes'
+        mode: '0777'
+        separator: ','
+        address: 127.0.0.1
+        env:
+          'true': '1.0'
+        tagged: !Port 80
+    "};
+    assert_eq!(to_string_with_options(&[&config], options), expected);
+
+    let options = SerializerOptions::new()
+        .quote_keys(QuotePolicy::AlwaysDouble)
+        .quote_values(QuotePolicy::AlwaysSingle);
+    let expected = indoc! {r#"
+        "name": 'web'
+        "enabled": 'yes'
+        "mode": '0777'
+        "separator": ','
+        "address": '127.0.0.1'
+        "env":
+          "true": '1.0'
+        "tagged": !Port 80
+    "#};
+    assert_eq!(to_string_with_options(&[&config], options), expected);
+
+    let options = SerializerOptions::new().quote_values(QuotePolicy::AlwaysDouble);
+    let expected = indoc! {r#"
+        name: "web"
+        enabled: "yes"
+        mode: "0777"
+        separator: ","
+        address: "127.0.0.1"
+        env:
+          'true': "1.0"
+        tagged: !Port 80
+    "#};
+    assert_eq!(to_string_with_options(&[&config], options), expected);
+}
+
+#[test]
+fn test_flow_style() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Check {
+        Http { path: String, port: u16 },
+        Exec(Vec<String>),
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Service {
+        ports: Vec<u16>,
+        labels: BTreeMap<String, String>,
+        volumes: Vec<Vec<String>>,
+        checks: Vec<Check>,
+        script: String,
+        #[serde(with = "serde_yaml::with::flow")]
+        command: Vec<String>,
+    }
+
+    fn to_string(service: &Service, options: SerializerOptions) -> String {
+        let yaml = to_string_with_options(&[service], options);
+        assert_eq!(serde_yaml::from_str::<Service>(&yaml).unwrap(), *service);
+        yaml
+    }
+
+    let service = Service {
+        ports: vec![80, 443],
+        labels: BTreeMap::from([
+            ("app".to_owned(), "web".to_owned()),
+            ("tier".to_owned(), "frontend".to_owned()),
+        ]),
+        volumes: vec![
+            vec!["data".to_owned(), "/var/lib/data".to_owned()],
+            vec!["logs".to_owned(), "/var/log".to_owned()],
+        ],
+        checks: vec![
+            Check::Http {
+                path: "/health".to_owned(),
+                port: 80,
+            },
+            Check::Exec(vec!["true".to_owned()]),
+        ],
+        script: "make\nmake install\n".to_owned(),
+        command: vec![
+            "nginx".to_owned(),
+            "-g".to_owned(),
+            "daemon off;".to_owned(),
+        ],
+    };
+
+    let expected = indoc! {"
+        ports:
+        - 80
+        - 443
+        labels:
+          app: web
+          tier: frontend
+        volumes:
+        - - data
+          - /var/lib/data
+        - - logs
+          - /var/log
+        checks:
+        - !Http
+          path: /health
+          port: 80
+        - !Exec
+          - 'true'
+        script: |
+          make
+          make install
+        command: [nginx, -g, daemon off;]
+    "};
+    assert_eq!(to_string(&service, SerializerOptions::new()), expected);
+
+    let options = SerializerOptions::new().flow_max_width(31);
+    let expected = indoc! {"
+        ports: [80, 443]
+        labels: {app: web, tier: frontend}
+        volumes:
+        - [data, /var/lib/data]
+        - [logs, /var/log]
+        checks:
+        - !Http {path: /health, port: 80}
+        - !Exec ['true']
+        script: |
+          make
+          make install
+        command: [nginx, -g, daemon off;]
+    "};
+    assert_eq!(to_string(&service, options), expected);
+
+    let options = SerializerOptions::new().flow_max_width(50);
+    let expected = indoc! {"
+        ports: [80, 443]
+        labels: {app: web, tier: frontend}
+        volumes: [[data, /var/lib/data], [logs, /var/log]]
+        checks: [!Http {path: /health, port: 80}, !Exec ['true']]
+        script: |
+          make
+          make install
+        command: [nginx, -g, daemon off;]
+    "};
+    assert_eq!(to_string(&service, options), expected);
+
+    let options = SerializerOptions::new().flow_leaves(true);
+    let expected = indoc! {"
+        ports: [80, 443]
+        labels: {app: web, tier: frontend}
+        volumes:
+        - [data, /var/lib/data]
+        - [logs, /var/log]
+        checks:
+        - !Http {path: /health, port: 80}
+        - !Exec ['true']
+        script: |
+          make
+          make install
+        command: [nginx, -g, daemon off;]
+    "};
+    assert_eq!(to_string(&service, options), expected);
+
+    let options = SerializerOptions::new()
+        .flow_leaves(true)
+        .flow_max_width(20);
+    let expected = indoc! {"
+        ports: [80, 443]
+        labels:
+          app: web
+          tier: frontend
+        volumes:
+        - - data
+          - /var/lib/data
+        - [logs, /var/log]
+        checks:
+        - !Http
+          path: /health
+          port: 80
+        - !Exec ['true']
+        script: |
+          make
+          make install
+        command: [nginx, -g, daemon off;]
+    "};
+    assert_eq!(to_string(&service, options), expected);
+}
+
+#[test]
+fn test_document_markers_and_directives() {
+    let documents: Vec<Value> = vec![
+        serde_yaml::from_str("kind: !io.k8s.api.core.v1.Pod\n  containers: [web]\n").unwrap(),
+        serde_yaml::from_str("!io.k8s.api.core.v1.Service 80").unwrap(),
+    ];
+
+    let options = SerializerOptions::new()
+        .explicit_start(true)
+        .explicit_end(true);
+    let expected = indoc! {"
+        ---
+        kind: !io.k8s.api.core.v1.Pod
+          containers:
+          - web
+        ...
+        --- !io.k8s.api.core.v1.Service 80
+        ...
+    "};
+    assert_eq!(to_string_with_options(&documents, options), expected);
+
+    let options = SerializerOptions::new()
+        .indent_sequences(true)
+        .version_directive(1, 2)
+        .tag_directive("!k8s!", "!io.k8s.api.");
+    let expected = indoc! {"
+        %YAML 1.2
+        %TAG !k8s! !io.k8s.api.
+        ---
+        kind: !k8s!core.v1.Pod
+          containers:
+            - web
+        ...
+        %YAML 1.2
+        %TAG !k8s! !io.k8s.api.
+        --- !k8s!core.v1.Service 80
+    "};
+    let yaml = to_string_with_options(&documents, options);
+    assert_eq!(yaml, expected);
+    let deserialized: Vec<Value> = serde_yaml::Deserializer::from_str(&yaml)
+        .map(Value::deserialize)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(deserialized, documents);
+
+    let mut ser =
+        Serializer::new(Vec::new()).with_options(SerializerOptions::new().version_directive(2, 0));
+    let error = documents[1].serialize(&mut ser).unwrap_err();
+    assert_eq!(error.to_string(), "incompatible %YAML directive");
+}